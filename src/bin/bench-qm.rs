@@ -0,0 +1,111 @@
+//! Benchmark the steps of QM on a batch of random INIT values.
+
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use clap::{Arg, Command};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use logic_minimization::{
+    convert::{init_hex_len, init_to_minterms_with_vars},
+    *,
+};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = Command::new("Benchmark QM")
+        .arg(
+            Arg::new("count")
+                .short('c')
+                .long("count")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1000")
+                .help("Number of random INIT values to minimize."),
+        )
+        .arg(
+            Arg::new("vars")
+                .short('n')
+                .long("vars")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("6")
+                .help("Number of variables of the random functions."),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("0")
+                .help("Seed for generating the INIT values."),
+        )
+        .get_matches();
+
+    let count = *matches.get_one::<usize>("count").unwrap();
+    let num_vars = *matches.get_one::<usize>("vars").unwrap();
+    let seed = *matches.get_one::<u64>("seed").unwrap();
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inits = (0..count)
+        .map(|_| random_init(&mut rng, num_vars))
+        .collect::<Vec<_>>();
+
+    let mut convert_time = Duration::ZERO;
+    let mut prime_time = Duration::ZERO;
+    let mut chart_time = Duration::ZERO;
+    let mut cover_time = Duration::ZERO;
+    let mut num_prime_impls = 0;
+    let mut num_cover_terms = 0;
+
+    for init in &inits {
+        let start_time = Instant::now();
+        let minterms = init_to_minterms_with_vars(init, num_vars)?;
+        convert_time += start_time.elapsed();
+
+        let start_time = Instant::now();
+        let prime_impls: Vec<Minterm> = get_prime_implicants(&minterms)?.into_iter().collect();
+        prime_time += start_time.elapsed();
+        num_prime_impls += prime_impls.len();
+
+        let start_time = Instant::now();
+        let chart = create_prime_implicant_chart(&prime_impls, &minterms)?;
+        chart_time += start_time.elapsed();
+
+        // Petrick's method is exponential on some inputs, so use the
+        // greedy cover to keep the run time predictable.
+        let start_time = Instant::now();
+        num_cover_terms += greedy_min_sop::get_minimal_sops(chart, prime_impls)?.len();
+        cover_time += start_time.elapsed();
+    }
+
+    println!("Minimized {count} random {num_vars}-variable INIT values (seed {seed}):");
+    println!("  {num_prime_impls} prime implicants, {num_cover_terms} cover terms in total.\n");
+    let total_time = convert_time + prime_time + chart_time + cover_time;
+    for (step, time) in [
+        ("Convert INIT to minterms", convert_time),
+        ("Generate prime implicants", prime_time),
+        ("Create prime implicant chart", chart_time),
+        ("Find greedy cover", cover_time),
+        ("Total", total_time),
+    ] {
+        println!(
+            "  {step:<30} {:>9.3} ms  ({:>7.2} us / INIT)",
+            time.as_secs_f64() * 1e3,
+            time.as_secs_f64() * 1e6 / count as f64
+        );
+    }
+
+    Ok(())
+}
+
+// A random hex init string for a function of `num_vars` variables.
+fn random_init(rng: &mut StdRng, num_vars: usize) -> String {
+    let mut init = (0..init_hex_len(num_vars))
+        .map(|_| format!("{:X}", rng.random_range(0..16)))
+        .collect::<String>();
+    // Functions of fewer than 2 variables only use the low bits of a hex char.
+    if num_vars < 2 {
+        let mask = (1 << (1 << num_vars)) - 1;
+        init = format!("{:X}", u32::from_str_radix(&init, 16).unwrap() & mask);
+    }
+    init
+}
@@ -9,7 +9,7 @@ const TEST_SOP_STRING: &str = "(A & !F) | (B & !C & D)";
 fn main() -> Result<(), Box<dyn Error>> {
     let sop_string = TEST_SOP_STRING;
     println!("Converting sum-of-products to INIT: {sop_string}");
-    let init_string = sop_string_to_init(sop_string);
+    let init_string = sop_string_to_init(sop_string)?;
     println!("INIT string: 16'h{init_string}");
 
     // Now convert back to sum-of-products string.
@@ -1,12 +1,20 @@
-//! Convert hex init string to sum-of-products string or vice-versa.
+//! Convert hex init string to sum-of-products string or vice-versa,
+//! or minimize functions read from or written to PLA files.
 
-use std::error::Error;
+use std::{error::Error, fs};
 
 use clap::{Arg, Command};
 use logic_minimization::{
     Minterm,
-    convert::{binary_strings_from_init_hex, sop_string_to_init, sop_to_minterms},
-    format::{display_sort_minterms, string_for_sop_minterms},
+    convert::{
+        binary_strings_from_init_hex, expand_minterms, init_to_minterms,
+        init_to_minterms_with_vars, minterms_to_init_with_vars, num_vars_for_init,
+        num_vars_for_sop, sop_to_minterms, sop_to_minterms_with_vars,
+    },
+    error::ParseError,
+    format::{display_sort_minterms, string_for_sop_minterms, var_name},
+    minimizer::Minimizer,
+    pla::{parse_pla, string_for_pla},
 };
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -25,6 +33,18 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .required(false)
                 .help("Hex init string to convert to sum-of-products."),
         )
+        .arg(
+            Arg::new("dc-sop")
+                .long("dc-sop")
+                .required(false)
+                .help("Sum-of-products expression for don't-cares to convert to hex init mask."),
+        )
+        .arg(
+            Arg::new("dc-init")
+                .long("dc-init")
+                .required(false)
+                .help("Hex init mask of don't-cares to convert to sum-of-products."),
+        )
         .arg(
             Arg::new("format-sop")
                 .short('f')
@@ -32,37 +52,214 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .required(false)
                 .help("Sum-of-products expression string to convert to sort and format."),
         )
+        .arg(
+            Arg::new("pla-in")
+                .long("pla-in")
+                .value_name("FILE")
+                .required(false)
+                .conflicts_with_all(["sum-of-products", "hex-init", "dc-sop", "dc-init"])
+                .help("PLA file of functions to convert to hex init strings, or to minimize with '--pla-out'."),
+        )
+        .arg(
+            Arg::new("pla-out")
+                .long("pla-out")
+                .value_name("FILE")
+                .required(false)
+                .help("PLA file to write the minimized cover to, of the PLA input or else the SoP or init; '-' for stdout."),
+        )
         .get_matches();
 
-    if let Some(sop_string) = matches.get_one::<String>("sum-of-products") {
-        let init_string = sop_string_to_init(sop_string);
-        println!("INIT value: 16'h{init_string}");
-    }
+    let sop_string = matches.get_one::<String>("sum-of-products");
+    let init = matches.get_one::<String>("hex-init");
+
+    let dc_sop_string = matches.get_one::<String>("dc-sop");
+    // The function and its don't-cares have the same number of variables.
+    let sop_num_vars = [sop_string, dc_sop_string]
+        .into_iter()
+        .flatten()
+        .map(|sop_string| or_exit(num_vars_for_sop(sop_string), sop_string))
+        .max();
+
+    let pla_out = matches.get_one::<String>("pla-out");
+    let dc_init = matches.get_one::<String>("dc-init");
+
+    // The PLA file may be written to stdout, so print nothing else then.
+    if pla_out.is_none() {
+        if let (Some(sop_string), Some(num_vars)) = (sop_string, sop_num_vars) {
+            let init_string = sop_string_to_init_with_vars(sop_string, num_vars);
+            println!("INIT value: {}", verilog_hex(&init_string));
+        }
+
+        if let Some(init) = init {
+            let term_strings = or_exit(binary_strings_from_init_hex(init), init);
+            let minterms = term_strings
+                .iter()
+                .map(|s| (&**s).into())
+                .collect::<Vec<Minterm>>();
+            let sop_string = string_for_sop_minterms(&minterms, true, Some("\n"));
+            println!(
+                "SoP string for init: ({} terms)\n  {sop_string}",
+                minterms.len()
+            );
+        }
 
-    if let Some(init) = matches.get_one::<String>("hex-init") {
-        let term_strings = binary_strings_from_init_hex(init)?;
-        let minterms = term_strings
-            .iter()
-            .map(|s| (&**s).into())
-            .collect::<Vec<Minterm>>();
-        let sop_string = string_for_sop_minterms(&minterms, true, Some("\n"));
-        println!(
-            "SoP string for init: ({} terms)\n  {sop_string}",
-            minterms.len()
-        );
+        if let (Some(dc_sop_string), Some(num_vars)) = (dc_sop_string, sop_num_vars) {
+            let dc_init_string = sop_string_to_init_with_vars(dc_sop_string, num_vars);
+            println!("Don't-care INIT mask: {}", verilog_hex(&dc_init_string));
+        }
+
+        if let Some(dc_init) = dc_init {
+            let dont_cares = or_exit(init_to_minterms(dc_init), dc_init);
+            let dc_sop_string = string_for_sop_minterms(&dont_cares, true, Some("\n"));
+            println!(
+                "SoP string for don't-care mask: ({} terms)\n  {dc_sop_string}",
+                dont_cares.len()
+            );
+        }
+
+        if let Some(sop_string) = matches.get_one::<String>("format-sop") {
+            let mut minterms = or_exit(sop_to_minterms(sop_string), sop_string);
+            display_sort_minterms(&mut minterms);
+            let sop_string = string_for_sop_minterms(&minterms, true, Some("\n"));
+            println!(
+                "Formatted SoP string: ({} terms)\n  {sop_string}",
+                minterms.len()
+            );
+        }
     }
 
-    if let Some(sop_string) = matches.get_one::<String>("format-sop") {
-        let mut minterms = sop_to_minterms(sop_string);
-        display_sort_minterms(&mut minterms);
-        let sop_string = string_for_sop_minterms(&minterms, true, Some("\n"));
-        println!(
-            "Formatted SoP string: ({} terms)\n  {sop_string}",
-            minterms.len()
-        );
+    if let Some(path) = matches.get_one::<String>("pla-in") {
+        let pla_string = fs::read_to_string(path)?;
+        let pla = or_exit_file(parse_pla(&pla_string), &pla_string, path);
+        if let Some(out_path) = pla_out {
+            let covers = pla
+                .outputs
+                .iter()
+                .map(|output| minimize(&output.on_set, &output.dont_cares))
+                .collect::<Result<Vec<_>, _>>()?;
+            let pla_string = string_for_pla(
+                &covers,
+                pla.num_inputs,
+                &pla.input_labels,
+                &pla.output_labels,
+            );
+            write_output(out_path, &pla_string)?;
+        } else {
+            for (i, output) in pla.outputs.iter().enumerate() {
+                let label = pla
+                    .output_labels
+                    .get(i)
+                    .cloned()
+                    .unwrap_or(format!("{}", i + 1));
+                println!(
+                    "INIT value for output {label}: {}",
+                    minterms_to_init_with_vars(&output.on_set, pla.num_inputs)
+                );
+                if !output.dont_cares.is_empty() {
+                    println!(
+                        "Don't-care INIT mask for output {label}: {}",
+                        minterms_to_init_with_vars(&output.dont_cares, pla.num_inputs)
+                    );
+                }
+            }
+        }
+    } else if let Some(out_path) = pla_out {
+        // A single function from the SoP or init, with the usual variable
+        // names, and the don't-cares over the same variables.
+        let num_vars = [init, dc_init]
+            .into_iter()
+            .flatten()
+            .map(|init| num_vars_for_init(init))
+            .chain(sop_num_vars)
+            .max()
+            .unwrap_or_default();
+        let minterms = if let Some(sop_string) = sop_string {
+            let terms = or_exit(sop_to_minterms_with_vars(sop_string, num_vars), sop_string);
+            expand_minterms(&terms)
+        } else if let Some(init) = init {
+            or_exit(init_to_minterms_with_vars(init, num_vars), init)
+        } else {
+            eprintln!("error: '--pla-out' needs a PLA, SoP or init input");
+            std::process::exit(1);
+        };
+        let mut dont_cares = vec![];
+        if let Some(dc_sop_string) = dc_sop_string {
+            let terms = or_exit(
+                sop_to_minterms_with_vars(dc_sop_string, num_vars),
+                dc_sop_string,
+            );
+            dont_cares.extend(expand_minterms(&terms));
+        }
+        if let Some(dc_init) = dc_init {
+            dont_cares.extend(or_exit(
+                init_to_minterms_with_vars(dc_init, num_vars),
+                dc_init,
+            ));
+        }
+        let var_names = (0..num_vars)
+            .map(|i| var_name(i).to_string())
+            .collect::<Vec<_>>();
+        let cover = minimize(&minterms, &dont_cares)?;
+        write_output(
+            out_path,
+            &string_for_pla(&[cover], num_vars, &var_names, &[]),
+        )?;
     }
 
     Ok(())
 }
 
+// Minimize one output of a function, keeping its cover.
+fn minimize(minterms: &[Minterm], dont_cares: &[Minterm]) -> Result<Vec<Minterm>, Box<dyn Error>> {
+    Ok(Minimizer::new().run(minterms, dont_cares)?.cover)
+}
+
+// Write `contents` to the file at `path`, or to stdout if it is `-`.
+fn write_output(path: &str, contents: &str) -> Result<(), Box<dyn Error>> {
+    if path == "-" {
+        print!("{contents}");
+    } else {
+        fs::write(path, contents)?;
+    }
+    Ok(())
+}
+
+/// Like `or_exit`, but for an error in the contents of the file at `path`,
+/// showing only the line with the problem.
+fn or_exit_file<T>(result: Result<T, ParseError>, contents: &str, path: &str) -> T {
+    result.unwrap_or_else(|mut err| {
+        let start = err.span.start.min(contents.len());
+        let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = contents[line_start..]
+            .find('\n')
+            .map_or(contents.len(), |i| line_start + i);
+        let line_num = contents[..line_start].matches('\n').count() + 1;
+        err.span = err.span.start - line_start..err.span.end.min(line_end) - line_start;
+        eprintln!("{path}:{line_num}:");
+        eprintln!("{}", err.render(contents[line_start..line_end].trim_end()));
+        std::process::exit(1);
+    })
+}
+
+// Convert an expression string to a hex init string for `num_vars` variables.
+fn sop_string_to_init_with_vars(sop_string: &str, num_vars: usize) -> String {
+    let minterms = or_exit(sop_to_minterms_with_vars(sop_string, num_vars), sop_string);
+    minterms_to_init_with_vars(&minterms, num_vars)
+}
+
+// Write a hex init string as a Verilog literal of its full width.
+fn verilog_hex(init_string: &str) -> String {
+    format!("{}'h{init_string}", 4 * init_string.len())
+}
+
+/// Unwrap a parse result, or print a diagnostic for the error in `input` and exit.
+fn or_exit<T>(result: Result<T, ParseError>, input: &str) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{}", err.render(input));
+        std::process::exit(1);
+    })
+}
+
 // For example: target/release/convert -s '(A & !F) | (B & !C & D)'
+//         or: target/release/convert -s '(A & !F)' --dc-sop '(B & !C & D)'
+//         or: target/release/convert --pla-in in.pla --pla-out out.pla
@@ -1,12 +1,26 @@
 //! Apply the Quine-McCluskey algorithm to minimize a logical expression.
 
-use std::error::Error;
+use std::{error::Error, time::Duration};
 
 use clap::{Arg, ArgAction, Command};
 
 use logic_minimization::{
-    convert::{init_to_minterms, sop_to_minterms},
-    format::{display_sort_minterms, string_for_sop_minterms},
+    convert::{
+        complement_minterms, expand_minterms, init_to_minterms_with_vars,
+        maxterms_to_init_with_vars, minterms_to_init_with_vars, num_vars_for_init,
+        num_vars_for_sop, sop_to_minterms_with_vars,
+    },
+    cost::{CostModel, GateInputs, LiteralCount, ProductCount, WeightedLiterals, cover_cost},
+    error::{ParseError, QmError},
+    format::{
+        display_sort_minterms, is_verilog_identifier, string_for_pos_maxterms,
+        string_for_sop_minterms, verilog_for_pos_maxterms, verilog_for_sop_minterms,
+        verilog_lut6_for_init,
+    },
+    greedy_min_sop::GreedyOptions,
+    petrick::PetrickBudget,
+    progress::{Monitor, Progress},
+    solver::{CoverSolver, ImprovedGreedy, Petrick, SatCover, SolverRegistry},
     *,
 };
 
@@ -14,12 +28,13 @@ const DEBUG: bool = false;
 const SEPARATOR: &str = "\n";
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let mut solvers = SolverRegistry::default();
     let matches = Command::new("Quine-McCluskey")
         .arg(
             Arg::new("init")
                 .short('i')
                 .long("init")
-                .help("Optional init string; up to 16 hex chars."),
+                .help("Optional init string; 2^n / 4 hex chars for n variables."),
         )
         .arg(
             Arg::new("sop")
@@ -27,36 +42,378 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .long("sop")
                 .help("Sum-of-products string of expression to minimize."),
         )
+        .arg(
+            Arg::new("multi-init")
+                .short('m')
+                .long("multi-init")
+                .num_args(1..)
+                .value_delimiter(',')
+                .conflicts_with_all([
+                    "init", "sop", "dc-init", "dc-sop", "pos", "cost", "weights", "all", "progress",
+                ])
+                .help("List of init strings for functions to minimize together, sharing products."),
+        )
+        .arg(
+            Arg::new("dc-init")
+                .long("dc-init")
+                .help("Optional init string mask of don't-care input combinations."),
+        )
+        .arg(
+            Arg::new("dc-sop")
+                .long("dc-sop")
+                .help("Sum-of-products string covering the don't-care input combinations."),
+        )
+        .arg(
+            Arg::new("vars")
+                .short('n')
+                .long("vars")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of variables; inferred from the input if not given."),
+        )
+        .arg(
+            Arg::new("pos")
+                .long("pos")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .help("Find a minimal product-of-sums instead of sum-of-products."),
+        )
+        .arg(
+            Arg::new("solver")
+                .long("solver")
+                .help(format!(
+                    "Algorithm for choosing a cover of the prime implicants: one of {}.",
+                    solvers.names().join(", ")
+                )),
+        )
         .arg(
             Arg::new("greedy")
                 .short('g')
                 .long("greedy")
                 .required(false)
-                .action(ArgAction::SetTrue),
+                .action(ArgAction::SetTrue)
+                .conflicts_with("solver")
+                .help("Use the greedy solver; short for '--solver greedy'."),
+        )
+        .arg(
+            Arg::new("branch-bound")
+                .short('b')
+                .long("branch-bound")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["solver", "greedy"])
+                .help("Use the exact branch and bound solver; short for '--solver branch-bound'."),
+        )
+        .arg(
+            Arg::new("all")
+                .long("all")
+                .num_args(0..=1)
+                .value_name("K")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with_all(["solver", "greedy", "branch-bound"])
+                .help("Find all minimal covers, or the first K of them, using Petrick's method."),
+        )
+        .arg(
+            Arg::new("espresso")
+                .long("espresso")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["solver", "greedy", "branch-bound", "all", "multi-init"])
+                .help("Use the Espresso-style heuristic minimizer, without generating all prime implicants."),
+        )
+        .arg(
+            Arg::new("cost")
+                .long("cost")
+                .value_parser(["products", "literals", "gate-inputs"])
+                .help("Cost to minimize when choosing a cover; the default is products."),
+        )
+        .arg(
+            Arg::new("weights")
+                .long("weights")
+                .num_args(1..)
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(u64))
+                .conflicts_with("cost")
+                .help("List of literal costs for variables A, B, ... to minimize when choosing a cover."),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .value_parser(parse_seconds)
+                .conflicts_with_all(["greedy", "branch-bound", "all", "espresso"])
+                .help("Time limit for Petrick's method, after which the greedy cover is used."),
+        )
+        .arg(
+            Arg::new("max-products")
+                .long("max-products")
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with_all(["greedy", "branch-bound", "all", "espresso"])
+                .help("Limit on the products expanded by Petrick's method, beyond which the greedy cover is used."),
+        )
+        .arg(
+            Arg::new("restarts")
+                .long("restarts")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of randomized covers tried by the improved-greedy solver after the first."),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_parser(clap::value_parser!(u64))
+                .help("Seed for the random choices of the improved-greedy solver."),
+        )
+        .arg(
+            Arg::new("max-conflicts")
+                .long("max-conflicts")
+                .value_parser(clap::value_parser!(u64))
+                .help("Limit on the conflicts of the sat solver, after which the best cover found is used."),
+        )
+        .arg(
+            Arg::new("emit")
+                .long("emit")
+                .value_parser(["verilog", "lut6"])
+                .conflicts_with_all(["all", "multi-init"])
+                .help("Also print a Verilog module for the minimized function: an assign statement, or a LUT6 primitive for at most 6 variables."),
+        )
+        .arg(
+            Arg::new("module")
+                .long("module")
+                .value_name("NAME")
+                .default_value("minimized")
+                .value_parser(parse_verilog_identifier)
+                .help("Name of the Verilog module printed by '--emit'."),
+        )
+        .arg(
+            Arg::new("var-names")
+                .long("var-names")
+                .value_name("NAMES")
+                .num_args(1..)
+                .value_delimiter(',')
+                .value_parser(parse_verilog_identifier)
+                .requires("emit")
+                .help("List of names of the input ports for variables A, B, ... of the module printed by '--emit'."),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .help("Print the progress of each phase of the minimization to stderr."),
         )
         .get_matches();
 
-    let use_greedy = matches.get_flag("greedy");
+    let budget = PetrickBudget {
+        timeout: matches.get_one::<Duration>("timeout").copied(),
+        max_products: matches.get_one::<usize>("max-products").copied(),
+    };
+    solvers.register(Box::new(Petrick::with_budget(budget)));
+    let defaults = GreedyOptions::default();
+    let greedy_options = GreedyOptions {
+        restarts: matches
+            .get_one::<usize>("restarts")
+            .copied()
+            .unwrap_or(defaults.restarts),
+        seed: matches
+            .get_one::<u64>("seed")
+            .copied()
+            .unwrap_or(defaults.seed),
+    };
+    solvers.register(Box::new(ImprovedGreedy::with_options(greedy_options)));
+    if let Some(max_conflicts) = matches.get_one::<u64>("max-conflicts") {
+        solvers.register(Box::new(SatCover::with_max_conflicts(*max_conflicts)));
+    }
+
+    let solver_name = match matches.get_one::<String>("solver") {
+        Some(name) => name.as_str(),
+        None if matches.get_flag("greedy") => "greedy",
+        None if matches.get_flag("branch-bound") => "branch-bound",
+        None => "petrick",
+    };
+    let Some(solver) = solvers.get(solver_name) else {
+        eprintln!(
+            "error: unknown solver '{solver_name}'; expected one of: {}",
+            solvers.names().join(", ")
+        );
+        std::process::exit(1);
+    };
+    // Only Petrick's method has a budget, after which it falls back to the greedy cover.
+    for budget_arg in ["timeout", "max-products"] {
+        if matches.contains_id(budget_arg) && solver_name != "petrick" {
+            eprintln!(
+                "error: '--{budget_arg}' applies only to the petrick solver, not '{solver_name}'"
+            );
+            std::process::exit(1);
+        }
+    }
+    let report_progress = |progress: Progress| eprintln!("progress: {progress:?}");
+    let monitor = if matches.get_flag("progress") {
+        Monitor::NONE.with_progress(&report_progress)
+    } else {
+        Monitor::NONE
+    };
+    let use_pos = matches.get_flag("pos");
+    let use_espresso = matches.get_flag("espresso");
+    let dc_init = matches.get_one::<String>("dc-init");
+    let dc_sop = matches.get_one::<String>("dc-sop");
+    let cost_model: Option<Box<dyn CostModel>> = match matches.get_one::<String>("cost") {
+        Some(cost) => match cost.as_str() {
+            "products" => Some(Box::new(ProductCount)),
+            "literals" => Some(Box::new(LiteralCount)),
+            "gate-inputs" => Some(Box::new(GateInputs)),
+            _ => unreachable!(),
+        },
+        None => matches.get_many::<u64>("weights").map(|weights| {
+            Box::new(WeightedLiterals {
+                weights: weights.copied().collect(),
+            }) as Box<dyn CostModel>
+        }),
+    };
+    let cost = cost_model.as_deref().unwrap_or(&ProductCount);
+
+    // Use the number of variables given, or else the largest needed for any input.
+    let num_vars = match matches.get_one::<usize>("vars") {
+        Some(num_vars) => *num_vars,
+        None => [
+            matches
+                .get_one::<String>("init")
+                .map(|s| num_vars_for_init(s)),
+            matches
+                .get_one::<String>("sop")
+                .map(|s| or_exit(num_vars_for_sop(s), s)),
+            dc_init.map(|s| num_vars_for_init(s)),
+            dc_sop.map(|s| or_exit(num_vars_for_sop(s), s)),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default(),
+    };
+    let var_names = matches
+        .get_many::<String>("var-names")
+        .map(|names| names.cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !var_names.is_empty() && var_names.len() != num_vars {
+        eprintln!(
+            "error: {} names given for the {num_vars} variables",
+            var_names.len()
+        );
+        std::process::exit(1);
+    }
+    if let Some((_, name)) = var_names
+        .iter()
+        .enumerate()
+        .find(|(i, name)| var_names[..*i].contains(name))
+    {
+        eprintln!("error: the variable name '{name}' is given more than once");
+        std::process::exit(1);
+    }
+    let emit = matches.get_one::<String>("emit").map(|form| Emit {
+        use_lut6: form == "lut6",
+        module_name: matches.get_one::<String>("module").unwrap(),
+        var_names: &var_names,
+        num_vars,
+    });
+    if emit
+        .as_ref()
+        .is_some_and(|emit| emit.use_lut6 && num_vars > 6)
+    {
+        eprintln!("error: a LUT6 has 6 inputs, but the function has {num_vars} variables");
+        std::process::exit(1);
+    }
+    if let Some(inits) = matches.get_many::<String>("multi-init") {
+        let inits = inits.collect::<Vec<_>>();
+        let num_vars = match matches.get_one::<usize>("vars") {
+            Some(num_vars) => *num_vars,
+            None => inits.iter().map(|s| num_vars_for_init(s)).max().unwrap(),
+        };
+        return minimize_multi_output(&inits, num_vars, solver);
+    }
+
     let mut minterms;
 
     if let Some(init) = matches.get_one::<String>("init") {
         // Convert hex init string to minterms for simplification.
-        minterms = init_to_minterms(init)?;
+        minterms = or_exit(init_to_minterms_with_vars(init, num_vars), init);
     } else if let Some(sop_string) = matches.get_one::<String>("sop") {
-        minterms = sop_to_minterms(sop_string);
+        minterms = or_exit(sop_to_minterms_with_vars(sop_string, num_vars), sop_string);
     } else {
         println!("No input provided. Please use --help to see input options.");
         return Ok(());
     }
 
+    let mut dont_cares = vec![];
+    if let Some(dc_init) = dc_init {
+        dont_cares.extend(or_exit(
+            init_to_minterms_with_vars(dc_init, num_vars),
+            dc_init,
+        ));
+    }
+    if let Some(dc_sop) = dc_sop {
+        let dc_terms = or_exit(sop_to_minterms_with_vars(dc_sop, num_vars), dc_sop);
+        // Espresso works on the product terms directly.
+        if use_espresso {
+            dont_cares.extend(dc_terms);
+        } else {
+            dont_cares.extend(expand_minterms(&dc_terms));
+        }
+    }
+
     display_sort_minterms(&mut minterms);
     println!(
         "Initial expression: ({} terms)\n  {}",
         minterms.len(),
         string_for_sop_minterms(&minterms, false, Some(SEPARATOR))
     );
+    if !dont_cares.is_empty() {
+        display_sort_minterms(&mut dont_cares);
+        println!(
+            "\nDon't-care set: ({} terms)\n  {}",
+            dont_cares.len(),
+            string_for_sop_minterms(&dont_cares, false, Some(SEPARATOR))
+        );
+    }
+
+    if use_espresso {
+        let cost_model = cost_model.as_deref();
+        let sops = minimize_espresso(
+            minterms,
+            &dont_cares,
+            num_vars,
+            use_pos,
+            cost_model.unwrap_or(&ProductCount),
+            &monitor,
+        )?;
+        print_cover(
+            sops,
+            use_pos,
+            "heuristically minimized",
+            cost_model,
+            emit.as_ref(),
+        )?;
+        return Ok(());
+    }
+
+    // For product-of-sums we minimize the complement, i.e. the OFF-set.
+    let target_minterms = if use_pos {
+        let mut care_terms = expand_minterms(&minterms);
+        care_terms.extend(dont_cares.iter().cloned());
+        let mut off_minterms = complement_minterms(&care_terms, num_vars);
+
+        display_sort_minterms(&mut off_minterms);
+        println!(
+            "\nOFF-set to minimize: ({} terms)\n  {}",
+            off_minterms.len(),
+            string_for_sop_minterms(&off_minterms, false, Some(SEPARATOR))
+        );
+        off_minterms
+    } else {
+        minterms
+    };
 
-    let mut prime_impls: Vec<Minterm> = get_prime_implicants(&minterms).into_iter().collect();
+    let mut prime_impls: Vec<Minterm> =
+        get_prime_implicants_with_monitor(&target_minterms, &dont_cares, &monitor)?
+            .into_iter()
+            .collect();
 
     display_sort_minterms(&mut prime_impls);
     println!(
@@ -65,24 +422,233 @@ fn main() -> Result<(), Box<dyn Error>> {
         string_for_sop_minterms(&prime_impls, false, Some(SEPARATOR))
     );
 
-    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, &minterms);
+    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, &target_minterms)?;
 
     if DEBUG {
         println!("\nPrime implicant chart:\n{prime_impl_chart:?}");
     }
 
-    let mut minimal_sops = if use_greedy {
-        greedy_min_sop::get_minimal_sops(prime_impl_chart, prime_impls)
+    if matches.contains_id("all") {
+        let max_covers = matches.get_one::<usize>("all").copied();
+        let (covers, num_covers) = match petrick::get_all_minimal_sop_terms_with_cost(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            max_covers,
+        ) {
+            Err(QmError::EmptyFunction) => (vec![vec![]], 1),
+            covers => covers?,
+        };
+        print_all_covers(covers, num_covers, use_pos);
+        return Ok(());
+    }
+
+    // A function with no minterms is covered by the empty sum.
+    let minimal_sops = match solver.solve(prime_impl_chart, prime_impls, cost, &monitor) {
+        Err(QmError::EmptyFunction) => vec![],
+        solution => {
+            let solution = solution?;
+            if solution.stats.budget_exceeded {
+                println!(
+                    "\nThe {} solver exceeded its budget, so its cover is not proven minimal.",
+                    solver.name()
+                );
+            }
+            solution.terms
+        }
+    };
+    print_cover(
+        minimal_sops,
+        use_pos,
+        "minimal",
+        cost_model.as_deref(),
+        emit.as_ref(),
+    )?;
+    Ok(())
+}
+
+fn minimize_espresso(
+    minterms: Vec<Minterm>,
+    dont_cares: &[Minterm],
+    num_vars: usize,
+    use_pos: bool,
+    cost: &dyn CostModel,
+    monitor: &Monitor,
+) -> Result<Vec<Minterm>, Box<dyn Error>> {
+    // As above, but complementing the cubes without enumerating minterms.
+    let on_set = if use_pos {
+        let care_terms = [minterms.as_slice(), dont_cares].concat();
+        let mut off_set = espresso::complement(&care_terms, num_vars);
+        display_sort_minterms(&mut off_set);
+        println!(
+            "\nOFF-set to minimize: ({} terms)\n  {}",
+            off_set.len(),
+            string_for_sop_minterms(&off_set, false, Some(SEPARATOR))
+        );
+        off_set
+    } else {
+        minterms
+    };
+
+    let (sops, stats) =
+        espresso::get_sop_terms_with_monitor(&on_set, dont_cares, num_vars, cost, monitor)?;
+    println!("\n{}", stats.get_report());
+    Ok(sops)
+}
+
+// Verilog module to print for the minimized function, from `--emit`.
+struct Emit<'a> {
+    use_lut6: bool,
+    module_name: &'a str,
+    var_names: &'a [String],
+    num_vars: usize,
+}
+
+impl Emit<'_> {
+    fn sop_module(&self, sops: &[Minterm]) -> Result<String, QmError> {
+        if self.use_lut6 {
+            let init = minterms_to_init_with_vars(sops, self.num_vars);
+            verilog_lut6_for_init(&init, self.num_vars, self.module_name, self.var_names)
+        } else {
+            verilog_for_sop_minterms(sops, self.num_vars, self.module_name, self.var_names)
+        }
+    }
+
+    fn pos_module(&self, maxterms: &[Minterm]) -> Result<String, QmError> {
+        if self.use_lut6 {
+            let init = maxterms_to_init_with_vars(maxterms, self.num_vars);
+            verilog_lut6_for_init(&init, self.num_vars, self.module_name, self.var_names)
+        } else {
+            verilog_for_pos_maxterms(maxterms, self.num_vars, self.module_name, self.var_names)
+        }
+    }
+}
+
+// Print a cover of the function, or of its OFF-set for product-of-sums,
+// with its cost if minimized for a cost model other than the default,
+// and then the Verilog module if asked for.
+fn print_cover(
+    mut sops: Vec<Minterm>,
+    use_pos: bool,
+    kind: &str,
+    cost: Option<&dyn CostModel>,
+    emit: Option<&Emit>,
+) -> Result<(), QmError> {
+    if let Some(cost) = cost {
+        println!("\nCost of the cover: {}", cover_cost(cost, &sops));
+    }
+    let module = if use_pos {
+        let mut maxterms = sops
+            .iter()
+            .map(Minterm::negated_literals)
+            .collect::<Vec<_>>();
+        display_sort_minterms(&mut maxterms);
+        println!(
+            "\nA {kind} equivalent product-of-sums: ({} terms)\n  {}",
+            maxterms.len(),
+            string_for_pos_maxterms(&maxterms, true, Some(SEPARATOR))
+        );
+        emit.map(|emit| emit.pos_module(&maxterms)).transpose()?
+    } else {
+        display_sort_minterms(&mut sops);
+        println!(
+            "\nA {kind} equivalent expression: ({} terms)\n  {}",
+            sops.len(),
+            string_for_sop_minterms(&sops, true, Some(SEPARATOR))
+        );
+        emit.map(|emit| emit.sop_module(&sops)).transpose()?
+    };
+    if let Some(module) = module {
+        print!("\nVerilog module:\n{module}");
+    }
+    Ok(())
+}
+
+fn print_all_covers(covers: Vec<Vec<Minterm>>, num_covers: usize, use_pos: bool) {
+    let kind = if use_pos {
+        "product-of-sums"
     } else {
-        petrick::get_minimal_sop_terms(prime_impl_chart, prime_impls).0
+        "expressions"
     };
+    println!(
+        "\nFound {num_covers} minimal equivalent {kind}, showing {}:",
+        covers.len()
+    );
+    for (i, mut terms) in covers.into_iter().enumerate() {
+        let expr_string = if use_pos {
+            let mut maxterms = terms
+                .iter()
+                .map(Minterm::negated_literals)
+                .collect::<Vec<_>>();
+            display_sort_minterms(&mut maxterms);
+            string_for_pos_maxterms(&maxterms, true, Some(SEPARATOR))
+        } else {
+            display_sort_minterms(&mut terms);
+            string_for_sop_minterms(&terms, true, Some(SEPARATOR))
+        };
+        println!(
+            "\nCover {}: ({} terms)\n  {expr_string}",
+            i + 1,
+            terms.len()
+        );
+    }
+}
+
+fn minimize_multi_output(
+    inits: &[&String],
+    num_vars: usize,
+    solver: &dyn CoverSolver,
+) -> Result<(), Box<dyn Error>> {
+    let functions = inits
+        .iter()
+        .map(|init| or_exit(init_to_minterms_with_vars(init, num_vars), init))
+        .collect::<Vec<_>>();
 
-    display_sort_minterms(&mut minimal_sops);
+    let cover = multi_output::get_minimal_multi_output_cover(&functions, solver)?;
+
+    let mut products = cover.products.iter().map(|p| p.term).collect::<Vec<_>>();
+    display_sort_minterms(&mut products);
     println!(
-        "\nA minimal equivalent expression: ({} terms)\n  {}",
-        minimal_sops.len(),
-        string_for_sop_minterms(&minimal_sops, true, Some(SEPARATOR))
+        "Shared products for {} outputs: ({} terms)\n  {}",
+        functions.len(),
+        products.len(),
+        string_for_sop_minterms(&products, false, Some(SEPARATOR))
     );
 
+    for (output, mut terms) in cover.outputs.into_iter().enumerate() {
+        display_sort_minterms(&mut terms);
+        println!(
+            "\nOutput {output} (INIT {}): ({} terms)\n  {}",
+            inits[output],
+            terms.len(),
+            string_for_sop_minterms(&terms, true, Some(SEPARATOR))
+        );
+    }
+
     Ok(())
 }
+
+/// Parse a non-negative number of seconds, for a clap argument.
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let secs = arg.parse::<f64>().map_err(|err| err.to_string())?;
+    Duration::try_from_secs_f64(secs).map_err(|err| err.to_string())
+}
+
+/// Check that a clap argument is a Verilog identifier, for a module name.
+fn parse_verilog_identifier(arg: &str) -> Result<String, String> {
+    if !is_verilog_identifier(arg) {
+        return Err(
+            "expected a Verilog identifier: a letter or '_', then letters, digits, '_' or '$'"
+                .into(),
+        );
+    }
+    Ok(arg.to_string())
+}
+
+/// Unwrap a parse result, or print a diagnostic for the error in `input` and exit.
+fn or_exit<T>(result: Result<T, ParseError>, input: &str) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{}", err.render(input));
+        std::process::exit(1);
+    })
+}
@@ -72,7 +72,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
 
         // Parse into minterm vector and count terms.
-        let sympy_minterms = sop_to_minterms(&sympy_result);
+        let sympy_minterms = sop_to_minterms(&sympy_result)?;
         println!("  Sympy result has {} minterms.", sympy_minterms.len());
 
         // Simplify with our code and count terms.
@@ -5,10 +5,22 @@ use std::time::Instant;
 
 use rand::Rng;
 
-use logic_minimization::{convert::sop_string_to_init, qm_simplify_init, qm_simplify_init_greedy};
+use logic_minimization::{
+    convert::{init_to_minterms, pos_string_to_init, sop_string_to_init},
+    espresso,
+    format::{display_sort_minterms, string_for_sop_minterms},
+    qm_simplify_init, qm_simplify_init_greedy, qm_simplify_init_pos, qm_simplify_with_solver,
+    solver::{BranchBound, CoverSolver, ImprovedGreedy, PetrickZdd, SatCover},
+};
 
 const NUM_CASES: usize = 200;
 const USE_GREEDY: bool = true;
+const USE_IMPROVED_GREEDY: bool = true;
+const USE_POS: bool = true;
+const USE_BRANCH_BOUND: bool = true;
+const USE_PETRICK_ZDD: bool = true;
+const USE_SAT: bool = true;
+const USE_ESPRESSO: bool = true;
 const DEBUG_INITS: bool = false;
 
 #[allow(unused)]
@@ -23,19 +35,51 @@ struct ExampleInfo {
     num_prime_impls: u32,
 }
 
+/// How far an approximate solver's covers are from the exact ones.
+#[derive(Default)]
+struct GapInfo {
+    num_cases: usize,
+    num_worse: usize,
+    extra_terms: usize,
+    max_extra_terms: usize,
+}
+
+impl GapInfo {
+    fn add(&mut self, num_terms: usize, num_exact_terms: usize) {
+        let extra = num_terms.saturating_sub(num_exact_terms);
+        self.num_cases += 1;
+        self.num_worse += usize::from(extra > 0);
+        self.extra_terms += extra;
+        self.max_extra_terms = self.max_extra_terms.max(extra);
+    }
+
+    fn report(&self, name: &str) {
+        println!(
+            "- {name}: {} of {} covers worse than exact, by {} terms in total (at most {}).",
+            self.num_worse, self.num_cases, self.extra_terms, self.max_extra_terms
+        );
+    }
+}
+
 fn main() {
     let mut rng = rand::rng();
+    // Each case runs several checks, any of which can fail.
+    let mut num_checks = 0;
     let mut failures = 0;
     let mut example_info = vec![];
+    let mut greedy_gap = GapInfo::default();
+    let mut improved_greedy_gap = GapInfo::default();
+    let mut espresso_gap = GapInfo::default();
 
     for i in 0..NUM_CASES {
         let init: u64 = rng.random_range(0..=u64::MAX);
         let init_string = format!("{init:016X}");
 
+        num_checks += 1;
         print!("{:02}: Testing INIT value {init_string} ... ", i + 1);
 
         let (sop_string, num_minterms, time_millis) = timed_qm(&init_string, false);
-        let return_init = sop_string_to_init(&sop_string);
+        let return_init = sop_string_to_init(&sop_string).expect("SOP conversion failed.");
 
         match init_string == return_init {
             true => println!("PASSED ({num_minterms} minterms)."),
@@ -58,14 +102,150 @@ fn main() {
             });
         }
 
+        if USE_POS {
+            num_checks += 1;
+            print!("--: Testing INIT value {init_string} as PRODUCT-OF-SUMS ... ");
+
+            let start_time = Instant::now();
+            let (pos_string, num_maxterms, _time) =
+                qm_simplify_init_pos(&init_string).expect("Init conversion failed.");
+            let time_millis = start_time.elapsed().as_millis();
+            let return_init = pos_string_to_init(&pos_string).expect("POS conversion failed.");
+
+            match init_string == return_init {
+                true => println!("PASSED ({num_maxterms} maxterms)."),
+                false => {
+                    println!("FAILED. Round trip INIT was: {return_init}.");
+                    failures += 1;
+                }
+            }
+            if DEBUG_INITS {
+                println!("    minimal POS: {pos_string}");
+            }
+            println!("    QM time: {time_millis} ms");
+        }
+
+        if USE_BRANCH_BOUND {
+            num_checks += 1;
+            print!("--: Testing INIT value {init_string} using BRANCH AND BOUND ... ");
+
+            let start_time = Instant::now();
+            let (bnb_string, num_bnb_terms) = solver_qm(&init_string, &BranchBound);
+            let time_millis = start_time.elapsed().as_millis();
+            let return_init = sop_string_to_init(&bnb_string).expect("SOP conversion failed.");
+
+            // The cover is exact, so should have as few terms as Petrick's.
+            match (init_string == return_init, num_bnb_terms == num_minterms) {
+                (true, true) => println!("PASSED ({num_bnb_terms} minterms)."),
+                (false, _) => {
+                    println!("FAILED. Round trip INIT was: {return_init}.");
+                    failures += 1;
+                }
+                (true, false) => {
+                    println!("FAILED. Got {num_bnb_terms} minterms instead of {num_minterms}.");
+                    failures += 1;
+                }
+            }
+            if DEBUG_INITS {
+                println!("    minimal SOP: {bnb_string}");
+            }
+            println!("    QM time: {time_millis} ms");
+        }
+
+        if USE_PETRICK_ZDD {
+            num_checks += 1;
+            print!("--: Testing INIT value {init_string} using PETRICK ZDD ... ");
+
+            let start_time = Instant::now();
+            let (zdd_string, num_zdd_terms) = solver_qm(&init_string, &PetrickZdd);
+            let time_millis = start_time.elapsed().as_millis();
+
+            // The ZDD backend should choose the very same cover as Petrick's.
+            if zdd_string == sop_string {
+                println!("PASSED ({num_zdd_terms} minterms).");
+            } else {
+                println!("FAILED. Got a different cover: {zdd_string}.");
+                failures += 1;
+            }
+            println!("    QM time: {time_millis} ms");
+        }
+
+        if USE_SAT {
+            num_checks += 1;
+            print!("--: Testing INIT value {init_string} using SAT ... ");
+
+            let start_time = Instant::now();
+            let (sat_string, num_sat_terms) = solver_qm(&init_string, &SatCover::EXACT);
+            let time_millis = start_time.elapsed().as_millis();
+            let return_init = sop_string_to_init(&sat_string).expect("SOP conversion failed.");
+
+            // The cover is exact, so should have as few terms as Petrick's.
+            match (init_string == return_init, num_sat_terms == num_minterms) {
+                (true, true) => println!("PASSED ({num_sat_terms} minterms)."),
+                (false, _) => {
+                    println!("FAILED. Round trip INIT was: {return_init}.");
+                    failures += 1;
+                }
+                (true, false) => {
+                    println!("FAILED. Got {num_sat_terms} minterms instead of {num_minterms}.");
+                    failures += 1;
+                }
+            }
+            println!("    QM time: {time_millis} ms");
+        }
+
+        if USE_IMPROVED_GREEDY {
+            num_checks += 1;
+            print!("--: Testing INIT value {init_string} using IMPROVED GREEDY approximation ... ");
+
+            let start_time = Instant::now();
+            let (greedy_string, num_greedy_terms) =
+                solver_qm(&init_string, &ImprovedGreedy::default());
+            let time_millis = start_time.elapsed().as_millis();
+            let return_init = sop_string_to_init(&greedy_string).expect("SOP conversion failed.");
+            improved_greedy_gap.add(num_greedy_terms, num_minterms);
+
+            match init_string == return_init {
+                true => println!("PASSED ({num_greedy_terms} minterms)."),
+                false => {
+                    println!("FAILED. Round trip INIT was: {return_init}.");
+                    failures += 1;
+                }
+            }
+            println!("    QM time: {time_millis} ms");
+        }
+
+        if USE_ESPRESSO {
+            num_checks += 1;
+            print!("--: Testing INIT value {init_string} using ESPRESSO heuristic ... ");
+
+            let start_time = Instant::now();
+            let (espresso_string, num_espresso_terms) = espresso_qm(&init_string);
+            let time_millis = start_time.elapsed().as_millis();
+            let return_init = sop_string_to_init(&espresso_string).expect("SOP conversion failed.");
+            espresso_gap.add(num_espresso_terms, num_minterms);
+
+            match init_string == return_init {
+                true => println!("PASSED ({num_espresso_terms} minterms)."),
+                false => {
+                    println!("FAILED. Round trip INIT was: {return_init}.");
+                    failures += 1;
+                }
+            }
+            println!("    QM time: {time_millis} ms");
+        }
+
         if !USE_GREEDY {
             continue;
         }
 
+        num_checks += 1;
         print!("--: Testing INIT value {init_string} using GREEDY approximation ... ");
 
+        let num_exact_terms = num_minterms;
         let (sop_string, num_minterms, time_millis) = timed_qm(&init_string, true);
-        let return_init = sop_string_to_init(&sop_string);
+        let return_init = sop_string_to_init(&sop_string).expect("SOP conversion failed.");
+        greedy_gap.add(num_minterms, num_exact_terms);
 
         match init_string == return_init {
             true => println!("PASSED ({num_minterms} minterms)."),
@@ -82,9 +262,24 @@ fn main() {
 
     println!("\n========\n");
     println!("Results:\n");
-    println!("- Passes: {}", NUM_CASES - failures);
+    println!("- Cases: {NUM_CASES}, with {num_checks} checks");
+    println!("- Passes: {}", num_checks - failures);
     println!("- Failures: {}\n", failures);
 
+    if USE_GREEDY || USE_IMPROVED_GREEDY || USE_ESPRESSO {
+        println!("Gap of the approximate covers versus exact:\n");
+        if USE_GREEDY {
+            greedy_gap.report("Greedy");
+        }
+        if USE_IMPROVED_GREEDY {
+            improved_greedy_gap.report("Improved greedy");
+        }
+        if USE_ESPRESSO {
+            espresso_gap.report("Espresso");
+        }
+        println!();
+    }
+
     if !example_info.is_empty() {
         println!("Long-running examples:");
         for example in example_info {
@@ -108,3 +303,22 @@ fn timed_qm(init_str: &str, greedy: bool) -> (String, usize, u128) {
 
     (sop_string, num_minterms, elapsed)
 }
+
+fn solver_qm(init_str: &str, solver: &dyn CoverSolver) -> (String, usize) {
+    let minterms = init_to_minterms(init_str).expect("Init conversion failed.");
+    let (sop_string, num_terms, _stats) =
+        qm_simplify_with_solver(&minterms, &[], solver).expect("Solver failed.");
+    (sop_string, num_terms)
+}
+
+fn espresso_qm(init_str: &str) -> (String, usize) {
+    let minterms = init_to_minterms(init_str).expect("Init conversion failed.");
+    let num_vars = minterms.first().map_or(6, |minterm| minterm.num_vars());
+    let (mut terms, _stats) =
+        espresso::get_sop_terms(&minterms, &[], num_vars).expect("Espresso failed.");
+    display_sort_minterms(&mut terms);
+    (
+        string_for_sop_minterms(&terms, true, Some(" ")),
+        terms.len(),
+    )
+}
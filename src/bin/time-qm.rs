@@ -36,7 +36,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
 
     let start_time = Instant::now();
-    let prime_impls: Vec<Minterm> = get_prime_implicants(&minterms).into_iter().collect();
+    let prime_impls: Vec<Minterm> = get_prime_implicants(&minterms)?.into_iter().collect();
     let elapsed = start_time.elapsed().as_millis();
     println!(
         "(*) {elapsed:>4} ms - Generated prime {} implicants.",
@@ -44,16 +44,69 @@ fn main() -> Result<(), Box<dyn Error>> {
     );
 
     let start_time = Instant::now();
-    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, &minterms);
+    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, &minterms)?;
     let elapsed = start_time.elapsed().as_millis();
     println!("(*) {elapsed:>4} ms - Created prime implicant chart.");
 
     let start_time = Instant::now();
-    let (mut minimal_sops, time) = petrick::get_minimal_sop_terms(prime_impl_chart, prime_impls);
+    let branch_bound_chart = create_prime_implicant_chart(&prime_impls, &minterms)?;
+    let branch_bound_sops =
+        branch_bound::get_minimal_sop_terms(branch_bound_chart, prime_impls.clone())?;
     let elapsed = start_time.elapsed().as_millis();
+    println!(
+        "(*) {elapsed:>4} ms - Simplified using branch and bound ({} terms).",
+        branch_bound_sops.len()
+    );
+
+    let zdd_inputs = (prime_impl_chart.clone(), prime_impls.clone());
+    #[cfg(feature = "parallel")]
+    let serial_inputs = (prime_impl_chart.clone(), prime_impls.clone());
+
+    let start_time = Instant::now();
+    let (mut minimal_sops, time) = petrick::get_minimal_sop_terms(prime_impl_chart, prime_impls)?;
+    let petrick_elapsed = start_time.elapsed();
+    let elapsed = petrick_elapsed.as_millis();
     println!("(*) {elapsed:>4} ms - Simplified using Petrick's method.");
 
+    // Compare with the serial path, by running in a single-thread pool.
+    #[cfg(feature = "parallel")]
+    {
+        let (serial_chart, serial_prime_impls) = serial_inputs;
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+        let start_time = Instant::now();
+        let (serial_sops, _) =
+            pool.install(|| petrick::get_minimal_sop_terms(serial_chart, serial_prime_impls))?;
+        let serial_elapsed = start_time.elapsed();
+        assert!(
+            serial_sops == minimal_sops,
+            "parallel result differs from serial"
+        );
+        println!(
+            "(*) {:>4} ms - Simplified using Petrick's method on one thread \
+             (speedup {:.2}x on {} threads).",
+            serial_elapsed.as_millis(),
+            serial_elapsed.as_secs_f64() / petrick_elapsed.as_secs_f64(),
+            rayon::current_num_threads()
+        );
+    }
+
+    // Compare with Petrick's method on a ZDD, which should give the same cover.
+    let (zdd_chart, zdd_prime_impls) = zdd_inputs;
+    let start_time = Instant::now();
+    let (zdd_sops, zdd_stats) = petrick_zdd::get_minimal_sop_terms(zdd_chart, zdd_prime_impls)?;
+    let zdd_elapsed = start_time.elapsed();
+    assert!(
+        zdd_sops == minimal_sops,
+        "ZDD result differs from Petrick's method"
+    );
+    println!(
+        "(*) {:>4} ms - Simplified using Petrick's method on a ZDD (speedup {:.2}x).",
+        zdd_elapsed.as_millis(),
+        petrick_elapsed.as_secs_f64() / zdd_elapsed.as_secs_f64()
+    );
+
     println!("\n{}", time.get_report());
+    println!("{}", zdd_stats.get_report());
 
     display_sort_minterms(&mut minimal_sops);
     println!(
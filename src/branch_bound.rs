@@ -0,0 +1,380 @@
+//! Find an exact minimum cover of the prime implicant chart by branch and
+//! bound. Before each branch the chart is reduced by selecting essential
+//! rows and removing dominated rows and columns, and branches are pruned
+//! using a lower bound from a set of columns with no rows in common.
+//!
+//! Gives a cover of the same cost as Petrick's method, without expanding
+//! the full product of sums, so is much faster on charts with many cycles.
+
+use crate::{
+    Minterm, PrimeImplicateChart,
+    cost::{CostModel, ProductCount, cover_key},
+    error::QmError,
+    petrick::remove_essential_prime_impls,
+    progress::{Monitor, Progress},
+};
+
+const DEV_DEBUG: bool = false;
+
+/// Number of nodes searched between progress reports.
+const NODES_PER_REPORT: usize = 1024;
+
+/// Get a minimal set of prime implicants for an equivalent expression.
+pub fn get_minimal_sop_terms(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+) -> Result<Vec<Minterm>, QmError> {
+    get_minimal_sop_terms_with_cost(prime_impl_chart, prime_impls, &ProductCount)
+}
+
+/// Get a set of prime implicants for an equivalent expression
+/// that is of minimal cost according to `cost`.
+pub fn get_minimal_sop_terms_with_cost(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+) -> Result<Vec<Minterm>, QmError> {
+    get_minimal_sop_terms_with_monitor(prime_impl_chart, prime_impls, cost, &Monitor::NONE)
+}
+
+/// Like `get_minimal_sop_terms_with_cost`, reporting the progress of the
+/// search to `monitor`, and stopping if it is cancelled.
+pub fn get_minimal_sop_terms_with_monitor(
+    mut prime_impl_chart: PrimeImplicateChart,
+    mut prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    monitor: &Monitor,
+) -> Result<Vec<Minterm>, QmError> {
+    prime_impl_chart.check_shape(&prime_impls)?;
+
+    // Remove essential prime implicants from chart.
+    let (mut min_expr_terms, remaining_cols) =
+        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, None)?;
+    monitor.report(Progress::EssentialPrimeImplicants {
+        num_essential: min_expr_terms.len(),
+        num_remaining_cols: remaining_cols.len(),
+    });
+    if remaining_cols.is_empty() {
+        // Indicates all prime impls were essential, so we're done.
+        return Ok(min_expr_terms);
+    }
+
+    let row_keys = prime_impls
+        .iter()
+        .map(|term| cover_key(cost, &[*term]))
+        .collect();
+    let mut search = CoverSearch::new(&prime_impl_chart, &remaining_cols, row_keys, monitor);
+    let state = CoverState {
+        rows: BitSet::full(search.row_cols.len()),
+        cols: BitSet::full(search.col_rows.len()),
+    };
+    search.branch(state, vec![])?;
+
+    if DEV_DEBUG {
+        println!("Branch and bound searched {} nodes.", search.num_nodes);
+    }
+    let Some((best, _)) = search.best else {
+        return Err(QmError::UncoveredColumn(remaining_cols[0]));
+    };
+    for row in best {
+        min_expr_terms.push(prime_impls[row]);
+    }
+    Ok(min_expr_terms)
+}
+
+// -----------------------------
+// Bit set of rows or columns.
+
+#[derive(Clone, PartialEq, Eq)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(len: usize) -> Self {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn full(len: usize) -> Self {
+        let mut set = BitSet::new(len);
+        for i in 0..len {
+            set.insert(i);
+        }
+        set
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.words[i / 64] &= !(1 << (i % 64));
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    /// Remove the elements of `other` from this set.
+    fn subtract(&mut self, other: &BitSet) {
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word &= !other_word;
+        }
+    }
+
+    fn intersection(&self, other: &BitSet) -> BitSet {
+        let words = self
+            .words
+            .iter()
+            .zip(&other.words)
+            .map(|(word, other_word)| word & other_word)
+            .collect();
+        BitSet { words }
+    }
+
+    fn intersects(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .any(|(word, other_word)| word & other_word != 0)
+    }
+
+    /// Number of elements of this set that are also in `other`.
+    fn count_in(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(word, other_word)| (word & other_word).count_ones() as usize)
+            .sum()
+    }
+
+    /// Check if the elements of this set in `within` are all in `other`.
+    fn is_subset_within(&self, other: &BitSet, within: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .zip(&within.words)
+            .all(|((word, other_word), within_word)| word & within_word & !other_word == 0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| 64 * i + bit)
+        })
+    }
+}
+
+// ----------------------------
+// Branch and bound search.
+
+/// Cost of a row or set of rows, and then number of literals, for comparing covers.
+type CoverKey = (u64, usize);
+
+fn add_keys(key: CoverKey, other: CoverKey) -> CoverKey {
+    (key.0 + other.0, key.1 + other.1)
+}
+
+/// The rows still available and the columns still to be covered.
+#[derive(Clone)]
+struct CoverState {
+    rows: BitSet,
+    cols: BitSet,
+}
+
+struct CoverSearch<'a> {
+    /// The columns covered by each row.
+    row_cols: Vec<BitSet>,
+    /// The rows covering each column.
+    col_rows: Vec<BitSet>,
+    row_keys: Vec<CoverKey>,
+    best: Option<(Vec<usize>, CoverKey)>,
+    num_nodes: usize,
+    monitor: Monitor<'a>,
+}
+
+impl<'a> CoverSearch<'a> {
+    /// Set up a search for a cover of columns `cols` of the chart,
+    /// where the rows have costs `row_keys`.
+    fn new(
+        prime_impl_chart: &PrimeImplicateChart,
+        cols: &[usize],
+        row_keys: Vec<CoverKey>,
+        monitor: &Monitor<'a>,
+    ) -> Self {
+        let num_rows = prime_impl_chart.rows.len();
+        let mut row_cols = vec![BitSet::new(cols.len()); num_rows];
+        let mut col_rows = vec![BitSet::new(num_rows); cols.len()];
+        for (row_i, row) in prime_impl_chart.rows.iter().enumerate() {
+            for (col_i, col) in cols.iter().enumerate() {
+                if row[*col] {
+                    row_cols[row_i].insert(col_i);
+                    col_rows[col_i].insert(row_i);
+                }
+            }
+        }
+        CoverSearch {
+            row_cols,
+            col_rows,
+            row_keys,
+            best: None,
+            num_nodes: 0,
+            monitor: *monitor,
+        }
+    }
+
+    /// Search for covers of `state` extending the rows `chosen`,
+    /// updating the best cover found. Fails if the search is cancelled.
+    fn branch(&mut self, mut state: CoverState, mut chosen: Vec<usize>) -> Result<(), QmError> {
+        self.monitor.check_cancelled()?;
+        self.num_nodes += 1;
+        if self.num_nodes.is_multiple_of(NODES_PER_REPORT) {
+            self.monitor.report(Progress::BranchBoundNodes {
+                num_nodes: self.num_nodes,
+                best_cost: self.best.as_ref().map(|(_, key)| key.0),
+            });
+        }
+        if !self.reduce(&mut state, &mut chosen) {
+            return Ok(());
+        }
+        let chosen_key = chosen
+            .iter()
+            .fold((0, 0), |key, row| add_keys(key, self.row_keys[*row]));
+        if state.cols.is_empty() {
+            if self
+                .best
+                .as_ref()
+                .is_none_or(|(_, best_key)| chosen_key < *best_key)
+            {
+                self.best = Some((chosen, chosen_key));
+            }
+            return Ok(());
+        }
+        // The bound is on cost only, since the literals of the
+        // chosen rows are already a bound on the number of literals.
+        if let Some((_, best_key)) = &self.best
+            && (chosen_key.0 + self.lower_bound(&state), chosen_key.1) >= *best_key
+        {
+            return Ok(());
+        }
+
+        // Branch on the rows covering the column with the fewest of them,
+        // trying rows that cover the most remaining columns first.
+        let col = state
+            .cols
+            .iter()
+            .min_by_key(|col| self.col_rows[*col].count_in(&state.rows))
+            .unwrap();
+        let mut rows = self.col_rows[col]
+            .intersection(&state.rows)
+            .iter()
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| std::cmp::Reverse(self.row_cols[*row].count_in(&state.cols)));
+
+        for row in rows {
+            let mut next_state = state.clone();
+            next_state.rows.remove(row);
+            next_state.cols.subtract(&self.row_cols[row]);
+            let mut next_chosen = chosen.clone();
+            next_chosen.push(row);
+            self.branch(next_state, next_chosen)?;
+
+            // All covers using this row have been searched.
+            state.rows.remove(row);
+        }
+        Ok(())
+    }
+
+    /// Reduce the chart by selecting essential rows, and removing dominated
+    /// rows and columns, until none remain. Returns `false` if some column
+    /// can no longer be covered.
+    fn reduce(&self, state: &mut CoverState, chosen: &mut Vec<usize>) -> bool {
+        loop {
+            let mut changed = false;
+
+            // Select rows that are the only row covering some column.
+            for col in state.cols.iter().collect::<Vec<_>>() {
+                if !state.cols.contains(col) {
+                    continue;
+                }
+                let rows = self.col_rows[col].intersection(&state.rows);
+                let mut rows = rows.iter();
+                match (rows.next(), rows.next()) {
+                    (None, _) => return false,
+                    (Some(row), None) => {
+                        chosen.push(row);
+                        state.rows.remove(row);
+                        state.cols.subtract(&self.row_cols[row]);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+
+            // Remove rows whose remaining columns are covered by another row
+            // of no greater cost.
+            let rows = state.rows.iter().collect::<Vec<_>>();
+            for row in rows.iter().copied() {
+                let row_cols = &self.row_cols[row];
+                let is_dominated = !row_cols.intersects(&state.cols)
+                    || rows.iter().any(|other| {
+                        *other != row
+                            && state.rows.contains(*other)
+                            && self.row_keys[*other] <= self.row_keys[row]
+                            && row_cols.is_subset_within(&self.row_cols[*other], &state.cols)
+                    });
+                if is_dominated {
+                    state.rows.remove(row);
+                    changed = true;
+                }
+            }
+
+            // Remove columns whose remaining rows all cover another column,
+            // since covering the other column covers this one too.
+            let cols = state.cols.iter().collect::<Vec<_>>();
+            for col in cols.iter().copied() {
+                let is_dominated = cols.iter().any(|other| {
+                    *other != col
+                        && state.cols.contains(*other)
+                        && self.col_rows[*other].is_subset_within(&self.col_rows[col], &state.rows)
+                });
+                if is_dominated {
+                    state.cols.remove(col);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return true;
+            }
+        }
+    }
+
+    /// A lower bound on the cost of covering the state's columns: the sum,
+    /// over a set of columns with no rows in common, of the least cost of
+    /// a row covering each column.
+    fn lower_bound(&self, state: &CoverState) -> u64 {
+        let mut cols = state.cols.iter().collect::<Vec<_>>();
+        cols.sort_by_key(|col| self.col_rows[*col].count_in(&state.rows));
+
+        let mut used_rows = BitSet::new(state.rows.words.len() * 64);
+        let mut bound = 0;
+        for col in cols {
+            let rows = self.col_rows[col].intersection(&state.rows);
+            if !rows.intersects(&used_rows) {
+                for row in rows.iter() {
+                    used_rows.insert(row);
+                }
+                bound += rows.iter().map(|row| self.row_keys[row].0).min().unwrap();
+            }
+        }
+        bound
+    }
+}
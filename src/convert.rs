@@ -1,154 +1,307 @@
 //! Code for converting between various representations of logical functions.
 //! The problem is simpler here than in the general case, since we restrict to
-//! functions with at most six variables.
+//! functions with at most `MAX_VARS` variables, whose truth tables we can
+//! afford to write out in full.
 
-use std::error::Error;
-
-use crate::{Minterm, format::string_for_minterm};
+use crate::{
+    MAX_VARS, Minterm,
+    error::ParseError,
+    expr::{Expr, parse_expr, parse_expr_with_vars},
+    format::string_for_minterm,
+};
 
 // ---------------------
 // Conversion functions.
 
 const DEV_DEBUG: bool = false;
 
+/// Smallest number of variables we use for INIT strings, so that
+/// short strings are read as 6-input LUT INIT values.
+pub const MIN_INIT_VARS: usize = 6;
+
+/// Number of variables of the function represented by a hex init string,
+/// inferred from its length. Strings of up to 16 hex chars are treated
+/// as functions of 6 variables, 32 hex chars as 7 variables, and so on.
+pub fn num_vars_for_init(hex_str: &str) -> usize {
+    let num_bits = 4 * hex_str.len();
+    let mut num_vars = MIN_INIT_VARS;
+    while (1_usize << num_vars) < num_bits {
+        num_vars += 1;
+    }
+    num_vars
+}
+
+/// Number of hex chars in a full init string for `num_vars` variables.
+pub fn init_hex_len(num_vars: usize) -> usize {
+    ((1_usize << num_vars) / 4).max(1)
+}
+
 /// Convert a hex "init" string to a list of binary term strings.
-pub fn binary_strings_from_init_hex(hex_str: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    const HEX_LEN: usize = 16;
-    if hex_str.len() > HEX_LEN {
-        return Err(String::from("Hex string contains more than 16 hex chars.").into());
+/// The number of variables is inferred from the length of the string.
+pub fn binary_strings_from_init_hex(hex_str: &str) -> Result<Vec<String>, ParseError> {
+    binary_strings_from_init_hex_with_vars(hex_str, num_vars_for_init(hex_str))
+}
+
+/// Convert a hex "init" string for a function of `num_vars`
+/// variables to a list of binary term strings.
+pub fn binary_strings_from_init_hex_with_vars(
+    hex_str: &str,
+    num_vars: usize,
+) -> Result<Vec<String>, ParseError> {
+    let indices = init_hex_to_indices(hex_str, num_vars)?;
+    Ok(indices.iter().map(|i| format!("{i:0num_vars$b}")).collect())
+}
+
+// Get the indices of the bits set in a hex init string
+// for a function of `num_vars` variables, in increasing order.
+fn init_hex_to_indices(hex_str: &str, num_vars: usize) -> Result<Vec<usize>, ParseError> {
+    if num_vars == 0 {
+        return Err(ParseError::new(
+            0..0,
+            "number of variables must be at least 1",
+        ));
+    }
+    check_num_vars(num_vars)?;
+    let hex_len = init_hex_len(num_vars);
+    if hex_str.len() > hex_len {
+        return Err(ParseError::new(
+            0..hex_str.len() - hex_len,
+            format!("init string for {num_vars} variables has more than {hex_len} hex chars"),
+        ));
     }
-    let zero_pad: String = std::iter::repeat_n('0', HEX_LEN - hex_str.len()).collect();
-    let hex_str = format!("{zero_pad}{hex_str}");
-    let num: u64 =
-        u64::from_str_radix(&hex_str, 16).expect("String is not a valid 64-bit hex string.");
 
     if DEV_DEBUG {
-        println!("As binary: {num:064b}");
+        println!("Init for {num_vars} variables: {hex_str:0>hex_len$}");
     }
-    let mut strings = vec![];
-    for i in 0..64 {
-        let mask: u64 = 1 << i;
-        if mask & num > 0 {
+    let mut indices = vec![];
+    for (nibble_i, (pos, ch)) in hex_str.char_indices().rev().enumerate() {
+        let span = pos..pos + ch.len_utf8();
+        let nibble = ch.to_digit(16).ok_or_else(|| {
+            ParseError::new(span.clone(), format!("invalid hex digit '{ch}'"))
+                .with_expected(&["hex digit 0-9 or A-F"])
+        })?;
+        for bit in 0..4 {
+            if nibble & (1 << bit) == 0 {
+                continue;
+            }
+            let i = 4 * nibble_i + bit;
+            if i >= 1 << num_vars {
+                return Err(ParseError::new(
+                    span,
+                    format!("init string has bits set beyond {num_vars} variables"),
+                ));
+            }
             if DEV_DEBUG {
-                println!("Term {i:02}: {i:06b}");
+                println!("Term {i:02}: {i:0num_vars$b}");
             }
-            strings.push(format!("{i:06b}"));
+            indices.push(i);
         }
     }
-    Ok(strings)
+    Ok(indices)
 }
 
-// We will take a simpler approach here, since we are dealing
-// with equations with a very specific form, e.g.:
-//  (A & !F) | (B & C & D)
-pub fn sop_string_to_init(sop_str: &str) -> String {
-    let minterms = sop_to_minterms(sop_str);
-    minterms_to_init(&minterms)
+// Check that we support functions of `num_vars` variables.
+fn check_num_vars(num_vars: usize) -> Result<(), ParseError> {
+    if num_vars > MAX_VARS {
+        return Err(ParseError::new(
+            0..0,
+            format!("number of variables must be at most {MAX_VARS}"),
+        ));
+    }
+    Ok(())
 }
 
-pub fn init_to_minterms(init_str: &str) -> Result<Vec<Minterm>, Box<dyn Error>> {
-    let term_strings = binary_strings_from_init_hex(init_str)?;
-    Ok(term_strings.iter().map(|s| (&**s).into()).collect())
+/// Convert an expression string, e.g. `(A & !F) | (B & C & D)`, to a hex init string.
+pub fn sop_string_to_init(sop_str: &str) -> Result<String, ParseError> {
+    let minterms = sop_to_minterms(sop_str)?;
+    Ok(minterms_to_init(&minterms))
 }
 
-pub fn sop_to_minterms(sop_str: &str) -> Vec<Minterm> {
-    let products = sop_str.trim().split('|');
-    let mut minterms = vec![];
-    for product in products {
-        let minterm = parse_product(product.trim());
-        if DEV_DEBUG {
-            println!(
-                "Product term {product} was parsed as term {}.",
-                string_for_minterm(&minterm)
-            );
-        }
-        minterms.push(minterm);
-    }
-    minterms
+pub fn init_to_minterms(init_str: &str) -> Result<Vec<Minterm>, ParseError> {
+    init_to_minterms_with_vars(init_str, num_vars_for_init(init_str))
 }
 
-const ALLOWED_VARS: &[char] = &['A', 'B', 'C', 'D', 'E', 'F', 'G'];
+pub fn init_to_minterms_with_vars(
+    init_str: &str,
+    num_vars: usize,
+) -> Result<Vec<Minterm>, ParseError> {
+    let indices = init_hex_to_indices(init_str, num_vars)?;
+    Ok(indices
+        .into_iter()
+        .map(|i| Minterm::from_index(i, num_vars))
+        .collect())
+}
 
-// Allows either `!` or '~' for negation.
-fn parse_product(prod_str: &str) -> Minterm {
-    let mut minterm = Minterm {
-        values: vec![b'x', b'x', b'x', b'x', b'x', b'x'],
-    };
-    let mut prod_ref = prod_str;
-    if !prod_str.starts_with('(') {
-        assert!(prod_str.len() == 1 && ALLOWED_VARS.contains(&prod_str.chars().next().unwrap()));
-    } else {
-        // We require nontrivial product enclosed in parentheses.
-        assert!(prod_str.starts_with('(') && prod_str.chars().nth_back(0) == Some(')'));
-        prod_ref = &prod_str.trim()[1..prod_str.len() - 1];
-    }
-    let terms = prod_ref.split('&');
+/// Get the OFF-set of the function given by a hex init string,
+/// i.e. the minterms for its zero bits.
+pub fn init_to_off_minterms(init_str: &str) -> Result<Vec<Minterm>, ParseError> {
+    init_to_off_minterms_with_vars(init_str, num_vars_for_init(init_str))
+}
+
+pub fn init_to_off_minterms_with_vars(
+    init_str: &str,
+    num_vars: usize,
+) -> Result<Vec<Minterm>, ParseError> {
+    let minterms = init_to_minterms_with_vars(init_str, num_vars)?;
+    Ok(complement_minterms(&minterms, num_vars))
+}
+
+/// Get the minterms over `num_vars` variables not covered by any of `terms`.
+pub fn complement_minterms(terms: &[Minterm], num_vars: usize) -> Vec<Minterm> {
+    let mut covered = vec![false; 1 << num_vars];
     for term in terms {
-        let term = term.trim();
-        if term.starts_with('!') || term.starts_with('~') {
-            let var = term
-                .chars()
-                .nth(1)
-                .expect("Expected variable to follow ! symbol.");
-            let var_i = char_to_index(var);
-            minterm.values[var_i] = b'0';
-        } else {
-            let var = term.chars().next().unwrap();
-            let var_i = char_to_index(var);
-            minterm.values[var_i] = b'1';
+        assert!(term.num_vars() == num_vars);
+        for i in term.covered_indices() {
+            covered[i] = true;
         }
     }
-    minterm
-}
-
-fn char_to_index(ch: char) -> usize {
-    match ch {
-        'A' => 5,
-        'B' => 4,
-        'C' => 3,
-        'D' => 2,
-        'E' => 1,
-        'F' => 0,
-        _ => panic!("Unexpected variable character: {ch}."),
+    covered
+        .iter()
+        .enumerate()
+        .filter(|(_, is_covered)| !**is_covered)
+        .map(|(i, _)| Minterm::from_index(i, num_vars))
+        .collect()
+}
+
+/// Number of variables of an expression string, inferred from the
+/// highest variable letter it contains. As for init strings, we use at
+/// least six variables.
+pub fn num_vars_for_sop(sop_str: &str) -> Result<usize, ParseError> {
+    Ok(parse_expr(sop_str)?.num_vars().max(MIN_INIT_VARS))
+}
+
+/// Parse a sum-of-products string into its product terms.
+/// The number of variables is inferred from the variables used.
+pub fn sop_to_minterms(sop_str: &str) -> Result<Vec<Minterm>, ParseError> {
+    sop_to_minterms_with_vars(sop_str, num_vars_for_sop(sop_str)?)
+}
+
+/// Parse an expression string into terms over `num_vars` variables. If the
+/// expression is a sum of products, e.g. `(A & ~F) | (B & C & D)`, we get
+/// its product terms; otherwise we evaluate it and get its minterms.
+pub fn sop_to_minterms_with_vars(
+    sop_str: &str,
+    num_vars: usize,
+) -> Result<Vec<Minterm>, ParseError> {
+    check_num_vars(num_vars)?;
+    let expr = parse_expr_with_vars(sop_str, num_vars)?;
+    let minterms = expr
+        .to_sop_terms(num_vars)
+        .unwrap_or_else(|| expr.to_minterms(num_vars));
+    if DEV_DEBUG {
+        for minterm in &minterms {
+            println!("Parsed product term {}.", string_for_minterm(minterm));
+        }
     }
+    Ok(minterms)
+}
+
+/// Parse an expression string and evaluate it to get the full
+/// set of minterms for the function over `num_vars` variables.
+pub fn expr_to_minterms(expr_str: &str, num_vars: usize) -> Result<Vec<Minterm>, ParseError> {
+    check_num_vars(num_vars)?;
+    Ok(parse_expr_with_vars(expr_str, num_vars)?.to_minterms(num_vars))
+}
+
+/// Parse a product-of-sums string, e.g. `(A | ~B) & (C)`, into maxterms
+/// whose values give the literals of each sum.
+pub fn pos_to_maxterms(pos_str: &str) -> Result<Vec<Minterm>, ParseError> {
+    pos_to_maxterms_with_vars(pos_str, num_vars_for_sop(pos_str)?)
+}
+
+/// Parse an expression string into maxterms over `num_vars` variables. If the
+/// expression is not a product of sums, we get the maxterms of its OFF-set.
+pub fn pos_to_maxterms_with_vars(
+    pos_str: &str,
+    num_vars: usize,
+) -> Result<Vec<Minterm>, ParseError> {
+    check_num_vars(num_vars)?;
+    let expr = parse_expr_with_vars(pos_str, num_vars)?;
+    Ok(expr.to_pos_terms(num_vars).unwrap_or_else(|| {
+        let off_minterms = Expr::Not(Box::new(expr)).to_minterms(num_vars);
+        off_minterms.iter().map(Minterm::negated_literals).collect()
+    }))
 }
 
+pub fn pos_string_to_init(pos_str: &str) -> Result<String, ParseError> {
+    let maxterms = pos_to_maxterms(pos_str)?;
+    Ok(maxterms_to_init(&maxterms))
+}
+
+/// Convert a list of product terms to a hex init string. The string
+/// has one bit for each input combination of `max(6, num_vars)` variables.
 pub fn minterms_to_init(minterms: &[Minterm]) -> String {
-    let mut init_num: u64 = 0;
+    let num_vars = minterms
+        .iter()
+        .map(Minterm::num_vars)
+        .fold(MIN_INIT_VARS, usize::max);
+    minterms_to_init_with_vars(minterms, num_vars)
+}
+
+/// Convert a list of product terms to a hex init string for `num_vars` variables.
+pub fn minterms_to_init_with_vars(minterms: &[Minterm], num_vars: usize) -> String {
+    let mut nibbles = vec![0_u8; init_hex_len(num_vars)];
     for minterm in minterms {
-        let init_terms = minterm_to_init_terms(minterm);
-        for term in init_terms {
-            let term_num = u8::from_str_radix(&term, 2).unwrap();
-            init_num |= 2_u64.pow(term_num as u32);
+        assert!(minterm.num_vars() <= num_vars);
+        for term_num in minterm.covered_indices() {
+            nibbles[term_num / 4] |= 1 << (term_num % 4);
         }
     }
-    format!("{init_num:016X}")
+    nibbles
+        .iter()
+        .rev()
+        .map(|nibble| format!("{nibble:X}"))
+        .collect()
 }
 
-fn minterm_to_init_terms(minterm: &Minterm) -> Vec<String> {
-    let mut init_terms = vec![String::new()];
-    for value in minterm.values.iter() {
-        match value {
-            b'0' => {
-                for term in init_terms.iter_mut() {
-                    *term += "0";
-                }
-            }
-            b'1' => {
-                for term in init_terms.iter_mut() {
-                    *term += "1";
-                }
-            }
-            b'x' => {
-                let mut one_terms = init_terms.clone();
-                one_terms.iter_mut().for_each(|term| *term += "1");
-                for term in init_terms.iter_mut() {
-                    *term += "0";
-                }
-                init_terms.extend(one_terms);
-            }
-            _ => unreachable!(),
-        }
-    }
-    init_terms
+/// Convert a list of maxterms, as from `pos_to_maxterms`, to a hex init string.
+pub fn maxterms_to_init(maxterms: &[Minterm]) -> String {
+    let num_vars = maxterms
+        .iter()
+        .map(Minterm::num_vars)
+        .fold(MIN_INIT_VARS, usize::max);
+    maxterms_to_init_with_vars(maxterms, num_vars)
+}
+
+/// Convert a list of maxterms to a hex init string for `num_vars` variables.
+pub fn maxterms_to_init_with_vars(maxterms: &[Minterm], num_vars: usize) -> String {
+    // Each sum is zero exactly on the product of its negated literals.
+    let zero_terms = maxterms
+        .iter()
+        .map(Minterm::negated_literals)
+        .collect::<Vec<_>>();
+    let zero_init = minterms_to_init_with_vars(&zero_terms, num_vars);
+    complement_init(&zero_init, num_vars)
+}
+
+/// Complement a full-length hex init string for `num_vars` variables.
+fn complement_init(init_str: &str, num_vars: usize) -> String {
+    // Mask for the valid bits of the leading hex char.
+    let top_mask = match num_vars {
+        0 => 0x1,
+        1 => 0x3,
+        _ => 0xF,
+    };
+    init_str
+        .chars()
+        .map(|ch| format!("{:X}", !ch.to_digit(16).unwrap() & top_mask))
+        .collect()
+}
+
+/// Expand a list of product terms into the distinct minterms they cover.
+pub fn expand_minterms(terms: &[Minterm]) -> Vec<Minterm> {
+    let mut indices = terms
+        .iter()
+        .flat_map(Minterm::covered_indices)
+        .collect::<Vec<_>>();
+    indices.sort();
+    indices.dedup();
+
+    let Some(num_vars) = terms.first().map(Minterm::num_vars) else {
+        return vec![];
+    };
+    indices
+        .iter()
+        .map(|i| Minterm::from_index(*i, num_vars))
+        .collect()
 }
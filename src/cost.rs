@@ -0,0 +1,70 @@
+//! Cost models for choosing between covers of a prime implicant chart.
+//!
+//! The cost of a cover is the sum of the costs of its product terms. The
+//! solvers pick the cover of least cost, breaking ties by fewest literals.
+
+use crate::Minterm;
+
+/// A cost for each product term in a cover.
+pub trait CostModel {
+    /// Cost of using `term` as one of the products of a cover.
+    fn term_cost(&self, term: &Minterm) -> u64;
+}
+
+/// Count the number of products in a cover.
+pub struct ProductCount;
+
+impl CostModel for ProductCount {
+    fn term_cost(&self, _term: &Minterm) -> u64 {
+        1
+    }
+}
+
+/// Count the number of literals in a cover.
+pub struct LiteralCount;
+
+impl CostModel for LiteralCount {
+    fn term_cost(&self, term: &Minterm) -> u64 {
+        term.num_literals() as u64
+    }
+}
+
+/// Sum a cost for each literal, depending on its variable. Variables
+/// past the end of `weights` have a cost of 1.
+pub struct WeightedLiterals {
+    pub weights: Vec<u64>,
+}
+
+impl CostModel for WeightedLiterals {
+    fn term_cost(&self, term: &Minterm) -> u64 {
+        (0..term.num_vars())
+            .filter(|var| term.literal(*var).is_some())
+            .map(|var| self.weights.get(var).copied().unwrap_or(1))
+            .sum()
+    }
+}
+
+/// Count the gate inputs of a two-level AND-OR circuit for the cover:
+/// an AND input for each literal of a product with more than one literal,
+/// and an OR input for each product.
+pub struct GateInputs;
+
+impl CostModel for GateInputs {
+    fn term_cost(&self, term: &Minterm) -> u64 {
+        match term.num_literals() {
+            0 | 1 => 1,
+            n => n as u64 + 1,
+        }
+    }
+}
+
+/// Total cost of the terms of a cover.
+pub fn cover_cost(cost: &dyn CostModel, terms: &[Minterm]) -> u64 {
+    terms.iter().map(|term| cost.term_cost(term)).sum()
+}
+
+/// Key for comparing covers, by cost and then by number of literals.
+pub fn cover_key(cost: &dyn CostModel, terms: &[Minterm]) -> (u64, usize) {
+    let num_literals = terms.iter().map(Minterm::num_literals).sum();
+    (cover_cost(cost, terms), num_literals)
+}
@@ -0,0 +1,136 @@
+//! Error types for the crate.
+
+use std::{fmt::Write, ops::Range};
+
+// ------------------
+// Parse error type.
+
+/// An error in user input such as an init string or expression,
+/// with the location of the problem in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte span of the offending part of the input.
+    pub span: Range<usize>,
+    /// Descriptions of the tokens that would have been accepted.
+    pub expected: Vec<String>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(span: Range<usize>, message: impl Into<String>) -> Self {
+        ParseError {
+            span,
+            expected: vec![],
+            message: message.into(),
+        }
+    }
+
+    pub fn with_expected(mut self, expected: &[&str]) -> Self {
+        self.expected = expected.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Render a diagnostic for the error, showing the `input` it
+    /// came from with the offending span underlined, e.g.:
+    ///
+    /// ```text
+    /// error: variable 'H' is out of range for 6 variables
+    ///   | (A & H)
+    ///   |      ^
+    ///   = expected one of: variable A-F
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let mut message = String::new();
+        writeln!(message, "error: {}", self.message).unwrap();
+
+        // Align the carets using char counts, so non-ASCII input lines up.
+        let start = input.floor_char_boundary(self.span.start.min(input.len()));
+        let end = input.ceil_char_boundary(self.span.end.clamp(start, input.len()));
+        let offset = input[..start].chars().count();
+        let width = input[start..end].chars().count().max(1);
+        writeln!(message, "  | {input}").unwrap();
+        write!(message, "  | {}{}", " ".repeat(offset), "^".repeat(width)).unwrap();
+
+        if !self.expected.is_empty() {
+            write!(
+                message,
+                "\n  = expected one of: {}",
+                self.expected.join(", ")
+            )
+            .unwrap();
+        }
+        message
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (at {}..{})",
+            self.message, self.span.start, self.span.end
+        )?;
+        if !self.expected.is_empty() {
+            write!(f, "; expected one of: {}", self.expected.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// ---------------------
+// Library error type.
+
+/// Errors returned by the minimization functions of the crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QmError {
+    /// Invalid user input, such as a malformed init string or expression.
+    InvalidInput(ParseError),
+    /// Terms with different numbers of variables were used together.
+    WidthMismatch { expected: usize, found: usize },
+    /// The function has no minterms, so there is nothing to cover.
+    EmptyFunction,
+    /// A column of the prime implicant chart that no row covers.
+    UncoveredColumn(usize),
+    /// A solver exceeded its time or size budget.
+    BudgetExceeded,
+    /// The minimization was cancelled through its `CancelToken`.
+    Cancelled,
+}
+
+impl std::fmt::Display for QmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QmError::InvalidInput(err) => write!(f, "invalid input: {err}"),
+            QmError::WidthMismatch { expected, found } => write!(
+                f,
+                "term has {found} variables, but expected {expected} variables"
+            ),
+            QmError::EmptyFunction => write!(f, "function has no minterms to cover"),
+            QmError::UncoveredColumn(col) => {
+                write!(
+                    f,
+                    "prime implicant chart column {col} is not covered by any row"
+                )
+            }
+            QmError::BudgetExceeded => write!(f, "solver exceeded its budget"),
+            QmError::Cancelled => write!(f, "minimization was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for QmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QmError::InvalidInput(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for QmError {
+    fn from(err: ParseError) -> Self {
+        QmError::InvalidInput(err)
+    }
+}
@@ -0,0 +1,479 @@
+//! Heuristic two-level minimization in the style of Espresso. Rather than
+//! generating all prime implicants, this works directly on a cover of cubes
+//! (i.e. product terms), improving it with the operations:
+//!
+//! - EXPAND: make each cube prime by removing literals while it stays
+//!   disjoint from the OFF-set, choosing literals so that it covers as many
+//!   other cubes of the cover as possible, which are then dropped.
+//! - IRREDUNDANT: drop cubes that are covered by the others and the
+//!   don't-cares, trying the most costly cubes first.
+//! - REDUCE: shrink each cube to the smallest cube containing the part of
+//!   it not covered by the rest of the cover, so that the next EXPAND can
+//!   grow it in a different direction.
+//!
+//! After an initial EXPAND and IRREDUNDANT, passes of REDUCE, EXPAND and
+//! IRREDUNDANT are made while they lower the cost of the cover. The result
+//! is a cover by prime implicants, no one of which can be dropped, but it
+//! isn't necessarily minimal.
+//!
+//! Covering and complementing are done with the unate recursive paradigm,
+//! splitting a cover on its most binate variable, so that only the OFF-set
+//! is ever expanded, and only as a list of cubes.
+
+use std::collections::HashSet;
+
+use crate::{
+    Minterm, check_num_vars,
+    cost::{CostModel, ProductCount, cover_key},
+    error::QmError,
+    progress::{Monitor, Progress},
+};
+
+/// Get a set of prime implicants for an expression equivalent to the
+/// product terms `on_set` of a function of `num_vars` variables, which
+/// may also cover any of the product terms `dont_cares`.
+pub fn get_sop_terms(
+    on_set: &[Minterm],
+    dont_cares: &[Minterm],
+    num_vars: usize,
+) -> Result<(Vec<Minterm>, EspressoStats), QmError> {
+    get_sop_terms_with_monitor(on_set, dont_cares, num_vars, &ProductCount, &Monitor::NONE)
+}
+
+/// Like `get_sop_terms`, but lowering the cost of the cover according to
+/// `cost`. Each pass is reported to `monitor`, stopping with
+/// `QmError::Cancelled` if it is cancelled.
+pub fn get_sop_terms_with_monitor(
+    on_set: &[Minterm],
+    dont_cares: &[Minterm],
+    num_vars: usize,
+    cost: &dyn CostModel,
+    monitor: &Monitor,
+) -> Result<(Vec<Minterm>, EspressoStats), QmError> {
+    let all_terms = [on_set, dont_cares, &[Minterm::new_true(num_vars)]].concat();
+    check_num_vars(&all_terms)?;
+
+    let mut cover = remove_contained(on_set.to_vec());
+    let care_set = [cover.as_slice(), dont_cares].concat();
+    let off_set = complement(&care_set, num_vars);
+    let mut stats = EspressoStats {
+        num_passes: 0,
+        num_off_cubes: off_set.len(),
+    };
+    if cover.is_empty() {
+        return Ok((cover, stats));
+    }
+
+    cover = expand(cover, &off_set, cost);
+    monitor.check_cancelled()?;
+    cover = irredundant(cover, dont_cares, cost);
+    loop {
+        monitor.report(Progress::EspressoPass {
+            pass: stats.num_passes,
+            num_cubes: cover.len(),
+            cost: cover_key(cost, &cover).0,
+        });
+        monitor.check_cancelled()?;
+        stats.num_passes += 1;
+        let reduced = reduce(cover.clone(), dont_cares, num_vars);
+        monitor.check_cancelled()?;
+        let expanded = expand(reduced, &off_set, cost);
+        monitor.check_cancelled()?;
+        let mut next_cover = irredundant(expanded, dont_cares, cost);
+        if cover_key(cost, &next_cover) >= cover_key(cost, &cover) {
+            monitor.check_cancelled()?;
+            next_cover = last_gasp(&cover, dont_cares, &off_set, num_vars, cost);
+            if cover_key(cost, &next_cover) >= cover_key(cost, &cover) {
+                break;
+            }
+        }
+        cover = next_cover;
+    }
+    Ok((cover, stats))
+}
+
+/// Statistics about a run of the Espresso-style minimizer.
+#[derive(Debug, Default)]
+pub struct EspressoStats {
+    /// Number of passes of REDUCE, EXPAND and IRREDUNDANT, including
+    /// the last one, which didn't improve the cover.
+    pub num_passes: usize,
+    /// Number of cubes in the computed OFF-set.
+    pub num_off_cubes: usize,
+}
+
+impl EspressoStats {
+    pub fn get_report(&self) -> String {
+        format!(
+            "Espresso made {} passes, with an OFF-set of {} cubes.",
+            self.num_passes, self.num_off_cubes
+        )
+    }
+}
+
+// ---------------
+// Cube operations.
+
+// Variables where both cubes have a literal, but with different values.
+// The cubes are disjoint exactly when there is one.
+fn conflicts(cube_1: &Minterm, cube_2: &Minterm) -> u32 {
+    (cube_1.value ^ cube_2.value) & cube_1.care & cube_2.care
+}
+
+// Whether `outer` covers every input combination that `inner` does.
+fn contains(outer: &Minterm, inner: &Minterm) -> bool {
+    outer.care & !inner.care == 0 && conflicts(outer, inner) == 0
+}
+
+// The part of `cube` inside `within`, with the literals of `within` removed,
+// or `None` if they are disjoint.
+fn cofactor(cube: &Minterm, within: &Minterm) -> Option<Minterm> {
+    if conflicts(cube, within) != 0 {
+        return None;
+    }
+    let care = cube.care & !within.care;
+    Some(Minterm {
+        value: cube.value & care,
+        care,
+        num_vars: cube.num_vars,
+    })
+}
+
+fn cofactor_cover(cover: &[Minterm], within: &Minterm) -> Vec<Minterm> {
+    cover
+        .iter()
+        .filter_map(|cube| cofactor(cube, within))
+        .collect()
+}
+
+// The cofactor of `cover` for variable `var` having value `value`.
+fn cofactor_var(cover: &[Minterm], var: usize, value: bool) -> Vec<Minterm> {
+    let mut within = Minterm::new_true(cover[0].num_vars());
+    within.set_literal(var, Some(value));
+    cofactor_cover(cover, &within)
+}
+
+// The smallest cube containing all of `cubes`, which must not be empty.
+fn supercube(cubes: &[Minterm]) -> Minterm {
+    let first = cubes[0];
+    let care = cubes.iter().fold(first.care, |care, cube| {
+        care & cube.care & !(cube.value ^ first.value)
+    });
+    Minterm {
+        value: first.value & care,
+        care,
+        num_vars: first.num_vars,
+    }
+}
+
+// Iterate over the variables with a bit set in `mask`.
+fn mask_vars(mut mask: u32) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (mask != 0).then(|| {
+            let var = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            var
+        })
+    })
+}
+
+// Drop the cubes of `cover` that are contained in another cube.
+fn remove_contained(mut cover: Vec<Minterm>) -> Vec<Minterm> {
+    // Check the larger cubes, i.e. with fewer literals, first.
+    cover.sort_by_key(Minterm::num_literals);
+    let mut kept: Vec<Minterm> = vec![];
+    for cube in cover {
+        if !kept.iter().any(|outer| contains(outer, &cube)) {
+            kept.push(cube);
+        }
+    }
+    kept
+}
+
+// ----------------------------------
+// Unate recursive cover operations.
+
+// The variable to split a cover on: the one with the most literals of both
+// values, or if there is none, the one with the most literals. Also gives
+// whether the cover is binate in it, i.e. has literals of both values.
+fn splitting_var(cover: &[Minterm]) -> Option<(usize, bool)> {
+    let num_vars = cover.first()?.num_vars();
+    let mut counts = vec![(0, 0); num_vars];
+    for cube in cover {
+        for var in mask_vars(cube.care) {
+            if cube.value & (1 << var) != 0 {
+                counts[var].1 += 1;
+            } else {
+                counts[var].0 += 1;
+            }
+        }
+    }
+    (0..num_vars)
+        .filter(|var| counts[*var] != (0, 0))
+        .map(|var| {
+            let (num_neg, num_pos) = counts[var];
+            (var, num_neg > 0 && num_pos > 0, num_neg + num_pos)
+        })
+        .max_by_key(|(_, is_binate, num_literals)| (*is_binate, *num_literals))
+        .map(|(var, is_binate, _)| (var, is_binate))
+}
+
+// Whether `cover` covers every input combination.
+fn is_tautology(cover: &[Minterm]) -> bool {
+    let Some(first) = cover.first() else {
+        return false;
+    };
+    if cover.iter().any(|cube| cube.care == 0) {
+        return true;
+    }
+    // Too few input combinations covered, even if the cubes are disjoint.
+    let num_vars = first.num_vars();
+    let num_covered = cover
+        .iter()
+        .map(|cube| 1_u64 << (num_vars - cube.num_literals()))
+        .sum::<u64>();
+    if num_covered < 1 << num_vars {
+        return false;
+    }
+    // A unate cover is a tautology only if it has the cube with no literals.
+    let Some((var, true)) = splitting_var(cover) else {
+        return false;
+    };
+    is_tautology(&cofactor_var(cover, var, false)) && is_tautology(&cofactor_var(cover, var, true))
+}
+
+// Whether `cover` covers every input combination of `cube`.
+fn covers_cube(cover: &[Minterm], cube: &Minterm) -> bool {
+    is_tautology(&cofactor_cover(cover, cube))
+}
+
+/// Get cubes covering the input combinations of a function of `num_vars`
+/// variables that aren't covered by any cube of `cover`. Unlike
+/// `convert::complement_minterms`, this doesn't enumerate the minterms.
+pub fn complement(cover: &[Minterm], num_vars: usize) -> Vec<Minterm> {
+    if cover.iter().any(|cube| cube.care == 0) {
+        return vec![];
+    }
+    let var = match cover {
+        [] => return vec![Minterm::new_true(num_vars)],
+        // De Morgan: the sum of the negated literals.
+        [cube] => {
+            return mask_vars(cube.care)
+                .map(|var| {
+                    let mut negated = Minterm::new_true(num_vars);
+                    negated.set_literal(var, cube.literal(var).map(|value| !value));
+                    negated
+                })
+                .collect();
+        }
+        _ => splitting_var(cover).unwrap().0,
+    };
+
+    let complement_neg = complement(&cofactor_var(cover, var, false), num_vars);
+    let complement_pos = complement(&cofactor_var(cover, var, true), num_vars);
+    // Cubes in the complement of both cofactors don't need the variable.
+    let mut pos_cubes = complement_pos.into_iter().collect::<HashSet<_>>();
+    let mut result = vec![];
+    for mut cube in complement_neg {
+        if !pos_cubes.remove(&cube) {
+            cube.set_literal(var, Some(false));
+        }
+        result.push(cube);
+    }
+    for mut cube in pos_cubes {
+        cube.set_literal(var, Some(true));
+        result.push(cube);
+    }
+    result
+}
+
+// -------------------------------------
+// EXPAND, IRREDUNDANT and REDUCE steps.
+
+// Make each cube of `cover` prime by removing literals while it stays
+// disjoint from `off_set`, and drop the cubes that it then contains.
+fn expand(mut cover: Vec<Minterm>, off_set: &[Minterm], cost: &dyn CostModel) -> Vec<Minterm> {
+    // Expand the larger cubes first, as they are the least likely to be
+    // covered by the expansion of another cube.
+    cover.sort_by_key(Minterm::num_literals);
+    let mut is_covered = vec![false; cover.len()];
+    let mut primes = vec![];
+    for i in 0..cover.len() {
+        if is_covered[i] {
+            continue;
+        }
+        let uncovered = (0..cover.len())
+            .filter(|j| !is_covered[*j] && *j != i)
+            .map(|j| cover[j])
+            .collect::<Vec<_>>();
+        let prime = expand_cube(cover[i], &uncovered, off_set, cost);
+        for (j, cube) in cover.iter().enumerate() {
+            if contains(&prime, cube) {
+                is_covered[j] = true;
+            }
+        }
+        primes.push(prime);
+    }
+    primes
+}
+
+// Remove literals from `cube` while it stays disjoint from `off_set`.
+// We first remove the literals needed to cover the most cubes of `others`,
+// and then those blocking the fewest cubes of the OFF-set.
+fn expand_cube(
+    mut cube: Minterm,
+    others: &[Minterm],
+    off_set: &[Minterm],
+    cost: &dyn CostModel,
+) -> Minterm {
+    // The cube stays disjoint from each OFF-set cube while it keeps one of
+    // the literals that conflict with it.
+    let mut blocking = off_set
+        .iter()
+        .map(|off_cube| conflicts(&cube, off_cube))
+        .collect::<Vec<_>>();
+    // Literals that would have to be removed to cover each other cube.
+    let mut needed = others
+        .iter()
+        .map(|other| conflicts(&cube, other) | (cube.care & !other.care))
+        .collect::<Vec<_>>();
+
+    loop {
+        // A literal can be removed unless it is the only one left
+        // blocking some OFF-set cube.
+        let mut essential = 0;
+        let mut num_blocking = [0_usize; u32::BITS as usize];
+        for lits in &blocking {
+            if lits.count_ones() == 1 {
+                essential |= lits;
+            }
+            for var in mask_vars(*lits) {
+                num_blocking[var] += 1;
+            }
+        }
+        let raisable = cube.care & !essential;
+        if raisable == 0 {
+            return cube;
+        }
+
+        // Count the other cubes that can still be covered needing each literal.
+        needed.retain(|lits| lits & !raisable == 0);
+        let mut num_needing = [0_usize; u32::BITS as usize];
+        for lits in &needed {
+            for var in mask_vars(*lits) {
+                num_needing[var] += 1;
+            }
+        }
+        let var = mask_vars(raisable)
+            .min_by_key(|var| {
+                let mut raised = cube;
+                raised.set_literal(*var, None);
+                (
+                    std::cmp::Reverse(num_needing[*var]),
+                    num_blocking[*var],
+                    cost.term_cost(&raised),
+                )
+            })
+            .unwrap();
+
+        cube.set_literal(var, None);
+        let bit = !(1 << var);
+        for lits in blocking.iter_mut().chain(needed.iter_mut()) {
+            *lits &= bit;
+        }
+        // Drop the cubes that are now covered, as they no longer count.
+        needed.retain(|lits| *lits != 0);
+    }
+}
+
+// Drop cubes of `cover` that are covered by the others and `dont_cares`,
+// trying the most costly cubes first.
+fn irredundant(
+    mut cover: Vec<Minterm>,
+    dont_cares: &[Minterm],
+    cost: &dyn CostModel,
+) -> Vec<Minterm> {
+    cover.sort_by_key(|cube| std::cmp::Reverse((cost.term_cost(cube), cube.num_literals())));
+    let mut is_kept = vec![true; cover.len()];
+    for i in 0..cover.len() {
+        let rest = cover
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| is_kept[*j] && *j != i)
+            .map(|(_, cube)| *cube)
+            .chain(dont_cares.iter().copied())
+            .collect::<Vec<_>>();
+        if covers_cube(&rest, &cover[i]) {
+            is_kept[i] = false;
+        }
+    }
+    cover
+        .into_iter()
+        .zip(is_kept)
+        .filter(|(_, is_kept)| *is_kept)
+        .map(|(cube, _)| cube)
+        .collect()
+}
+
+// Shrink each cube of `cover` in turn, as for `reduce_cube`, against the
+// cubes already reduced and those still to be.
+fn reduce(mut cover: Vec<Minterm>, dont_cares: &[Minterm], num_vars: usize) -> Vec<Minterm> {
+    // Reduce the larger cubes first, as they have the most to give up.
+    cover.sort_by_key(Minterm::num_literals);
+    let mut reduced = cover.into_iter().map(Some).collect::<Vec<_>>();
+    for i in 0..reduced.len() {
+        let cube = reduced[i].unwrap();
+        let rest = reduced
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .filter_map(|(_, cube)| *cube)
+            .chain(dont_cares.iter().copied())
+            .collect::<Vec<_>>();
+        reduced[i] = reduce_cube(&cube, &rest, num_vars);
+    }
+    reduced.into_iter().flatten().collect()
+}
+
+// Shrink `cube` to the smallest cube containing its input combinations that
+// aren't covered by `rest`, or `None` if there are none.
+fn reduce_cube(cube: &Minterm, rest: &[Minterm], num_vars: usize) -> Option<Minterm> {
+    // The uncovered part of the cube, with the cube's literals removed.
+    let uncovered = complement(&cofactor_cover(rest, cube), num_vars);
+    (!uncovered.is_empty()).then(|| {
+        let within = supercube(&uncovered);
+        Minterm {
+            value: cube.value | within.value,
+            care: cube.care | within.care,
+            num_vars: cube.num_vars,
+        }
+    })
+}
+
+// A last attempt at improving `cover` once the passes stop helping: reduce
+// each cube on its own against all the others, expand each of the reduced
+// cubes to cover others of them if it can, and add the new primes to the
+// cover in the hope that IRREDUNDANT can then drop more cubes.
+fn last_gasp(
+    cover: &[Minterm],
+    dont_cares: &[Minterm],
+    off_set: &[Minterm],
+    num_vars: usize,
+    cost: &dyn CostModel,
+) -> Vec<Minterm> {
+    let reduced = (0..cover.len())
+        .filter_map(|i| {
+            let rest = [&cover[..i], &cover[i + 1..], dont_cares].concat();
+            reduce_cube(&cover[i], &rest, num_vars)
+        })
+        .collect::<Vec<_>>();
+    let mut new_primes = vec![];
+    for (i, cube) in reduced.iter().enumerate() {
+        let others = [&reduced[..i], &reduced[i + 1..]].concat();
+        let prime = expand_cube(*cube, &others, off_set, cost);
+        if others.iter().any(|other| contains(&prime, other)) {
+            new_primes.push(prime);
+        }
+    }
+    irredundant([cover, &new_primes].concat(), dont_cares, cost)
+}
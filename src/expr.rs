@@ -0,0 +1,472 @@
+//! Parse Boolean expressions into an expression tree, which can be evaluated
+//! to get the truth table or minterm set of the function it represents.
+//!
+//! The grammar, from lowest to highest precedence, is:
+//!
+//! ```text
+//! expr    := implies (("<->" | "==") implies)*    XNOR (equivalence)
+//! implies := or ("->" implies)?                   implication, right-associative
+//! or      := xor (("|" | "+") xor)*
+//! xor     := and ("^" and)*
+//! and     := unary (("&" | "*") unary)*
+//! unary   := ("!" | "~") unary | primary
+//! primary := VAR | "0" | "1" | "True" | "False" | "(" expr ")"
+//! ```
+//!
+//! Variables are the single letters `A`-`Z`, with `A` the least-significant
+//! bit of a minterm index, as for init strings. Parentheses, negations and
+//! implications may be nested at most `MAX_NESTING` deep.
+
+use std::ops::Range;
+
+use crate::{MAX_VARS, Minterm, error::ParseError, format::var_name};
+
+// ---------------------
+// Expression tree type.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Const(bool),
+    /// Variable with index `i`, i.e. `A` for 0, `B` for 1, etc.
+    Var(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Xor(Box<Expr>, Box<Expr>),
+    Xnor(Box<Expr>, Box<Expr>),
+    Implies(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate the expression for the input combination `input`,
+    /// where bit `i` gives the value of variable `i`.
+    pub fn eval(&self, input: usize) -> bool {
+        match self {
+            Expr::Const(value) => *value,
+            Expr::Var(i) => input & (1 << i) != 0,
+            Expr::Not(e) => !e.eval(input),
+            Expr::And(a, b) => a.eval(input) && b.eval(input),
+            Expr::Or(a, b) => a.eval(input) || b.eval(input),
+            Expr::Xor(a, b) => a.eval(input) != b.eval(input),
+            Expr::Xnor(a, b) => a.eval(input) == b.eval(input),
+            Expr::Implies(a, b) => !a.eval(input) || b.eval(input),
+        }
+    }
+
+    /// Number of variables needed to evaluate the expression,
+    /// i.e. one more than the highest variable index used.
+    pub fn num_vars(&self) -> usize {
+        match self {
+            Expr::Const(_) => 0,
+            Expr::Var(i) => i + 1,
+            Expr::Not(e) => e.num_vars(),
+            Expr::And(a, b)
+            | Expr::Or(a, b)
+            | Expr::Xor(a, b)
+            | Expr::Xnor(a, b)
+            | Expr::Implies(a, b) => a.num_vars().max(b.num_vars()),
+        }
+    }
+
+    /// Get the truth table of the expression over `num_vars` variables.
+    pub fn truth_table(&self, num_vars: usize) -> Vec<bool> {
+        assert!(self.num_vars() <= num_vars && num_vars <= MAX_VARS);
+        (0..1_usize << num_vars).map(|i| self.eval(i)).collect()
+    }
+
+    /// Get the minterms of the expression over `num_vars` variables.
+    pub fn to_minterms(&self, num_vars: usize) -> Vec<Minterm> {
+        self.truth_table(num_vars)
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value)
+            .map(|(i, _)| Minterm::from_index(i, num_vars))
+            .collect()
+    }
+
+    /// If the expression is a sum of products of literals, get its
+    /// product terms over `num_vars` variables. Products containing
+    /// both a variable and its negation are left out.
+    pub fn to_sop_terms(&self, num_vars: usize) -> Option<Vec<Minterm>> {
+        let mut terms = vec![];
+        for product in self.operands(false) {
+            if let Some(term) = product.literals_term(num_vars, true)? {
+                terms.push(term);
+            }
+        }
+        Some(terms)
+    }
+
+    /// If the expression is a product of sums of literals, get its sum
+    /// terms over `num_vars` variables, as maxterms whose values give the
+    /// literals of each sum. Sums containing both a variable and its
+    /// negation are left out.
+    pub fn to_pos_terms(&self, num_vars: usize) -> Option<Vec<Minterm>> {
+        let mut terms = vec![];
+        for sum in self.operands(true) {
+            if let Some(term) = sum.literals_term(num_vars, false)? {
+                terms.push(term);
+            }
+        }
+        Some(terms)
+    }
+
+    // Flatten nested 'and's (if `is_and`) or 'or's into a list of operands.
+    fn operands(&self, is_and: bool) -> Vec<&Expr> {
+        match self {
+            Expr::And(a, b) if is_and => [a.operands(is_and), b.operands(is_and)].concat(),
+            Expr::Or(a, b) if !is_and => [a.operands(is_and), b.operands(is_and)].concat(),
+            _ => vec![self],
+        }
+    }
+
+    // Get the term for a product (if `is_product`) or sum of literals. Returns
+    // `None` if the expression is not of this form, and `Some(None)` if the
+    // term is trivial because it contains a variable and its negation.
+    fn literals_term(&self, num_vars: usize, is_product: bool) -> Option<Option<Minterm>> {
+        let literals = self.operands(is_product);
+
+        let mut term = Minterm::new_true(num_vars);
+        for literal in literals {
+            let (var, value) = match literal {
+                Expr::Var(i) => (*i, true),
+                Expr::Not(e) => match **e {
+                    Expr::Var(i) => (i, false),
+                    _ => return None,
+                },
+                // The identity for the operation can be dropped.
+                Expr::Const(value) if *value == is_product => continue,
+                _ => return None,
+            };
+            assert!(var < num_vars);
+            match term.literal(var) {
+                None => term.set_literal(var, Some(value)),
+                Some(current) if current != value => return Some(None),
+                _ => {}
+            }
+        }
+        Some(Some(term))
+    }
+}
+
+// ----------
+// Tokenizer.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Var(usize),
+    Const(bool),
+    Not,
+    And,
+    Or,
+    Xor,
+    Xnor,
+    Implies,
+    LParen,
+    RParen,
+    End,
+}
+
+impl TokenKind {
+    fn description(&self) -> String {
+        match self {
+            TokenKind::Var(i) => format!("variable '{}'", var_name(*i)),
+            TokenKind::Const(value) => format!("constant '{value}'"),
+            TokenKind::Not => "'~'".into(),
+            TokenKind::And => "'&'".into(),
+            TokenKind::Or => "'|'".into(),
+            TokenKind::Xor => "'^'".into(),
+            TokenKind::Xnor => "'<->'".into(),
+            TokenKind::Implies => "'->'".into(),
+            TokenKind::LParen => "'('".into(),
+            TokenKind::RParen => "')'".into(),
+            TokenKind::End => "end of input".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// Byte span of the token in the input.
+    span: Range<usize>,
+}
+
+// Tokens that can start an operand.
+const EXPECTED_OPERAND: &[&str] = &["variable", "constant", "'~'", "'('"];
+
+// Tokens that can follow an operand.
+const EXPECTED_OPERATOR: &[&str] = &["'&'", "'^'", "'|'", "'->'", "'<->'"];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let bytes = input.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'!' | b'~' => TokenKind::Not,
+            b'&' | b'*' => TokenKind::And,
+            b'|' | b'+' => TokenKind::Or,
+            b'^' => TokenKind::Xor,
+            b'(' => TokenKind::LParen,
+            b')' => TokenKind::RParen,
+            b'0' => TokenKind::Const(false),
+            b'1' => TokenKind::Const(true),
+            b'-' if bytes.get(i + 1) == Some(&b'>') => {
+                i += 1;
+                TokenKind::Implies
+            }
+            b'=' if bytes.get(i + 1) == Some(&b'=') => {
+                i += 1;
+                TokenKind::Xnor
+            }
+            b'<' if bytes[i..].starts_with(b"<->") => {
+                i += 2;
+                TokenKind::Xnor
+            }
+            ch if ch.is_ascii_alphabetic() => {
+                let word_len = bytes[i..]
+                    .iter()
+                    .take_while(|ch| ch.is_ascii_alphanumeric())
+                    .count();
+                let word = &input[i..i + word_len];
+                i += word_len - 1;
+                match word {
+                    "True" => TokenKind::Const(true),
+                    "False" => TokenKind::Const(false),
+                    _ if word_len == 1 && ch.is_ascii_uppercase() => {
+                        TokenKind::Var((ch - b'A') as usize)
+                    }
+                    _ => {
+                        return Err(ParseError::new(
+                            start..i + 1,
+                            format!("unknown name '{word}'"),
+                        )
+                        .with_expected(&[
+                            "variable A-Z",
+                            "'True'",
+                            "'False'",
+                        ]));
+                    }
+                }
+            }
+            _ => {
+                let ch = input[i..].chars().next().unwrap();
+                return Err(ParseError::new(
+                    start..start + ch.len_utf8(),
+                    format!("unexpected character '{ch}'"),
+                ));
+            }
+        };
+        i += 1;
+        tokens.push(Token {
+            kind,
+            span: start..i,
+        });
+    }
+    tokens.push(Token {
+        kind: TokenKind::End,
+        span: input.len()..input.len(),
+    });
+    Ok(tokens)
+}
+
+// -------------------------
+// Recursive-descent parser.
+
+/// Parse a Boolean expression string into an expression tree.
+pub fn parse_expr(input: &str) -> Result<Expr, ParseError> {
+    parse_expr_with_vars(input, MAX_VARS)
+}
+
+/// Parse a Boolean expression string for a function of `num_vars`
+/// variables, so that using any other variable is an error.
+pub fn parse_expr_with_vars(input: &str, num_vars: usize) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        num_vars,
+        depth: 0,
+    };
+    let expr = parser.parse_xnor()?;
+    let next = parser.peek();
+    if next.kind != TokenKind::End {
+        let expected = if next.kind == TokenKind::RParen {
+            "unmatched ')'".to_string()
+        } else {
+            format!("unexpected {}", next.kind.description())
+        };
+        return Err(ParseError::new(next.span.clone(), expected)
+            .with_expected(&[EXPECTED_OPERATOR, &["end of input"]].concat()));
+    }
+    Ok(expr)
+}
+
+// Join the operands of a chain of an associative operator into a balanced
+// tree, so that long sums or products don't give a deep tree, which would
+// overflow the stack when evaluated. The operands keep their order.
+fn balanced_tree(mut operands: Vec<Expr>, op: fn(Box<Expr>, Box<Expr>) -> Expr) -> Expr {
+    while operands.len() > 1 {
+        let mut pairs = operands.into_iter();
+        operands = std::iter::from_fn(|| {
+            let a = pairs.next()?;
+            Some(match pairs.next() {
+                Some(b) => op(Box::new(a), Box::new(b)),
+                None => a,
+            })
+        })
+        .collect();
+    }
+    operands.pop().unwrap()
+}
+
+/// Deepest nesting of parentheses, negations and implications that we parse,
+/// so that the recursion can't overflow the stack.
+pub const MAX_NESTING: usize = 256;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    num_vars: usize,
+    /// Number of enclosing parentheses, negations and implications.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn parse_xnor(&mut self) -> Result<Expr, ParseError> {
+        let mut operands = vec![self.parse_implies()?];
+        while self.peek().kind == TokenKind::Xnor {
+            self.next();
+            operands.push(self.parse_implies()?);
+        }
+        Ok(balanced_tree(operands, Expr::Xnor))
+    }
+
+    fn parse_implies(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.parse_or()?;
+        if self.peek().kind == TokenKind::Implies {
+            let op = self.next();
+            return Ok(Expr::Implies(
+                Box::new(expr),
+                Box::new(self.parse_nested(op.span, Self::parse_implies)?),
+            ));
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut operands = vec![self.parse_xor()?];
+        while self.peek().kind == TokenKind::Or {
+            self.next();
+            operands.push(self.parse_xor()?);
+        }
+        Ok(balanced_tree(operands, Expr::Or))
+    }
+
+    fn parse_xor(&mut self) -> Result<Expr, ParseError> {
+        let mut operands = vec![self.parse_and()?];
+        while self.peek().kind == TokenKind::Xor {
+            self.next();
+            operands.push(self.parse_and()?);
+        }
+        Ok(balanced_tree(operands, Expr::Xor))
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut operands = vec![self.parse_unary()?];
+        while self.peek().kind == TokenKind::And {
+            self.next();
+            operands.push(self.parse_unary()?);
+        }
+        Ok(balanced_tree(operands, Expr::And))
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().kind == TokenKind::Not {
+            let op = self.next();
+            return Ok(Expr::Not(Box::new(
+                self.parse_nested(op.span, Self::parse_unary)?,
+            )));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Var(i) if i >= self.num_vars => Err(ParseError::new(
+                token.span,
+                format!(
+                    "variable '{}' is out of range for {} variables",
+                    var_name(i),
+                    self.num_vars
+                ),
+            )
+            .with_expected(&[&self.var_range()])),
+            TokenKind::Var(i) => Ok(Expr::Var(i)),
+            TokenKind::Const(value) => Ok(Expr::Const(value)),
+            TokenKind::LParen => {
+                let expr = self.parse_nested(token.span, Self::parse_xnor)?;
+                let close = self.next();
+                if close.kind != TokenKind::RParen {
+                    return Err(ParseError::new(
+                        close.span,
+                        format!("expected ')', found {}", close.kind.description()),
+                    )
+                    .with_expected(&[EXPECTED_OPERATOR, &["')'"]].concat()));
+                }
+                Ok(expr)
+            }
+            kind => Err(ParseError::new(
+                token.span,
+                format!("expected an operand, found {}", kind.description()),
+            )
+            .with_expected(EXPECTED_OPERAND)),
+        }
+    }
+
+    // Parse with `parse` one level deeper, failing at the operator or
+    // parenthesis at `span` if that is nested too deeply.
+    fn parse_nested(
+        &mut self,
+        span: Range<usize>,
+        parse: fn(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        if self.depth == MAX_NESTING {
+            return Err(ParseError::new(
+                span,
+                format!("expression is nested more than {MAX_NESTING} deep"),
+            ));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    // Description of the allowed variables, e.g. "variable A-F".
+    fn var_range(&self) -> String {
+        match self.num_vars {
+            0 => "no variables".into(),
+            1 => "variable A".into(),
+            n => format!("variable A-{}", var_name(n - 1)),
+        }
+    }
+}
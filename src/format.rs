@@ -1,6 +1,9 @@
 //! Code to format and display logical expressions.
 
-use crate::Minterm;
+use crate::{
+    MAX_VARS, Minterm,
+    error::{ParseError, QmError},
+};
 
 // ----------------------------
 // String formatting functions.
@@ -14,31 +17,48 @@ pub fn display_sort_minterms(minterms: &mut [Minterm]) {
     if minterms.is_empty() {
         return;
     }
-    assert!(minterms.first().unwrap().values.len() == 6);
+    let num_vars = minterms.first().unwrap().num_vars();
+    assert!(minterms.iter().all(|m| m.num_vars() == num_vars));
+    // Order by the literal for `A`, then `B`, etc., with two bits per variable.
     minterms.sort_by_key(|m| {
-        let mut tuple = [2_u8; 6];
-        for (i, val) in m.values.iter().rev().enumerate() {
-            match val {
-                b'1' => tuple[i] = 0,
-                b'0' => tuple[i] = 1,
-                b'x' => tuple[i] = 2,
-                _ => unreachable!(),
-            }
-        }
-        tuple
+        (0..num_vars).fold(0_u64, |key, var| {
+            let rank = match m.literal(var) {
+                Some(true) => 0,
+                Some(false) => 1,
+                None => 2,
+            };
+            (key << 2) | rank
+        })
     });
 }
 
-const EQN_VARS: &[char] = &['A', 'B', 'C', 'D', 'E', 'F'];
+/// Name of the variable with index `i`, i.e. `A`, `B`, ...
+pub fn var_name(i: usize) -> char {
+    assert!(i < MAX_VARS);
+    (b'A' + i as u8) as char
+}
+
+// Name of variable `i`, from `var_names` if given there.
+fn var_name_in(i: usize, var_names: &[String]) -> String {
+    match var_names.get(i) {
+        Some(name) => name.clone(),
+        None => var_name(i).to_string(),
+    }
+}
 
 pub fn string_for_minterm(minterm: &Minterm) -> String {
+    string_for_minterm_with_names(minterm, &[])
+}
+
+/// Like `string_for_minterm`, but with variable `i` named by `var_names[i]`
+/// rather than a letter, for the variables it has a name for.
+pub fn string_for_minterm_with_names(minterm: &Minterm, var_names: &[String]) -> String {
     let mut term_string = String::new();
-    for (i, c) in minterm.values.iter().rev().enumerate() {
-        let var = match c {
-            b'x' => continue,
-            b'0' => format!("{NEG_CHAR}{}", EQN_VARS[i]),
-            b'1' => format!("{}", EQN_VARS[i]),
-            _ => unreachable!(),
+    for i in 0..minterm.num_vars() {
+        let var = match minterm.literal(i) {
+            None => continue,
+            Some(false) => format!("{NEG_CHAR}{}", var_name_in(i, var_names)),
+            Some(true) => var_name_in(i, var_names),
         };
         if term_string.is_empty() {
             term_string = var.to_string();
@@ -58,6 +78,16 @@ pub fn string_for_sop_minterms(
     minterms: &[Minterm],
     omit_trivial: bool,
     separator: Option<&str>,
+) -> String {
+    string_for_sop_minterms_with_names(minterms, omit_trivial, separator, &[])
+}
+
+/// Like `string_for_sop_minterms`, but with variables named by `var_names`.
+pub fn string_for_sop_minterms_with_names(
+    minterms: &[Minterm],
+    omit_trivial: bool,
+    separator: Option<&str>,
+    var_names: &[String],
 ) -> String {
     if minterms.is_empty() {
         return "False".into();
@@ -66,7 +96,7 @@ pub fn string_for_sop_minterms(
     let separator = separator.unwrap_or(" ");
     let mut expr_string = String::new();
     for minterm in minterms.iter() {
-        let term_string = string_for_minterm(minterm);
+        let term_string = string_for_minterm_with_names(minterm, var_names);
         if term_string == "True" && omit_trivial {
             continue;
         }
@@ -83,3 +113,225 @@ pub fn string_for_sop_minterms(
         expr_string
     }
 }
+
+/// Get a string representation for the sum with literals given by `maxterm`.
+pub fn string_for_maxterm(maxterm: &Minterm) -> String {
+    string_for_maxterm_with_names(maxterm, &[])
+}
+
+/// Like `string_for_maxterm`, but with variables named by `var_names`.
+pub fn string_for_maxterm_with_names(maxterm: &Minterm, var_names: &[String]) -> String {
+    let mut term_string = String::new();
+    for i in 0..maxterm.num_vars() {
+        let var = match maxterm.literal(i) {
+            None => continue,
+            Some(false) => format!("{NEG_CHAR}{}", var_name_in(i, var_names)),
+            Some(true) => var_name_in(i, var_names),
+        };
+        if term_string.is_empty() {
+            term_string = var.to_string();
+        } else {
+            term_string = format!("{term_string} | {var}");
+        }
+    }
+    if term_string.is_empty() {
+        "False".into()
+    } else {
+        term_string
+    }
+}
+
+/// Get a string representation for the POS with maxterm set `maxterms`.
+pub fn string_for_pos_maxterms(
+    maxterms: &[Minterm],
+    omit_trivial: bool,
+    separator: Option<&str>,
+) -> String {
+    string_for_pos_maxterms_with_names(maxterms, omit_trivial, separator, &[])
+}
+
+/// Like `string_for_pos_maxterms`, but with variables named by `var_names`.
+pub fn string_for_pos_maxterms_with_names(
+    maxterms: &[Minterm],
+    omit_trivial: bool,
+    separator: Option<&str>,
+    var_names: &[String],
+) -> String {
+    if maxterms.is_empty() {
+        return "True".into();
+    }
+
+    let separator = separator.unwrap_or(" ");
+    let mut expr_string = String::new();
+    for maxterm in maxterms.iter() {
+        let term_string = string_for_maxterm_with_names(maxterm, var_names);
+        if term_string == "False" && omit_trivial {
+            continue;
+        }
+        if expr_string.is_empty() {
+            expr_string = format!("({term_string})");
+        } else {
+            expr_string = format!("{expr_string}{separator}& ({term_string})");
+        }
+    }
+
+    if expr_string.is_empty() {
+        "False".into()
+    } else {
+        expr_string
+    }
+}
+
+// ---------------
+// Verilog output.
+
+// Separator between the terms of an assigned expression, one to a line.
+const VERILOG_SEPARATOR: &str = "\n        ";
+
+/// Whether `name` is a simple Verilog identifier: a letter or underscore,
+/// followed by any letters, digits, underscores and dollar signs.
+pub fn is_verilog_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '$')
+}
+
+/// Get a synthesizable Verilog module `module_name`, with an input port for
+/// each of `num_vars` variables named as for `string_for_sop_minterms_with_names`,
+/// and an output port `y` assigned the SOP with minterm set `minterms`.
+/// Fails if the module name or a variable name isn't a Verilog identifier,
+/// or if two variables have the same name.
+pub fn verilog_for_sop_minterms(
+    minterms: &[Minterm],
+    num_vars: usize,
+    module_name: &str,
+    var_names: &[String],
+) -> Result<String, QmError> {
+    check_verilog_names(num_vars, module_name, var_names)?;
+    let expr =
+        string_for_sop_minterms_with_names(minterms, true, Some(VERILOG_SEPARATOR), var_names);
+    Ok(verilog_module(
+        num_vars,
+        module_name,
+        var_names,
+        &verilog_assign(&expr),
+    ))
+}
+
+/// Like `verilog_for_sop_minterms`, but for the POS with maxterm set `maxterms`.
+pub fn verilog_for_pos_maxterms(
+    maxterms: &[Minterm],
+    num_vars: usize,
+    module_name: &str,
+    var_names: &[String],
+) -> Result<String, QmError> {
+    check_verilog_names(num_vars, module_name, var_names)?;
+    let expr =
+        string_for_pos_maxterms_with_names(maxterms, true, Some(VERILOG_SEPARATOR), var_names);
+    Ok(verilog_module(
+        num_vars,
+        module_name,
+        var_names,
+        &verilog_assign(&expr),
+    ))
+}
+
+/// Get a Verilog module like `verilog_for_sop_minterms`, but instantiating
+/// a `LUT6` primitive with the hex init string `init_str` for the `num_vars`
+/// variables, as from `minterms_to_init_with_vars`. The inputs of the LUT
+/// beyond the first `num_vars` are tied low, and the INIT zero-extended.
+/// Also fails if there are more than six variables or hex digits of INIT.
+pub fn verilog_lut6_for_init(
+    init_str: &str,
+    num_vars: usize,
+    module_name: &str,
+    var_names: &[String],
+) -> Result<String, QmError> {
+    if num_vars > 6 {
+        return Err(invalid_verilog(format!(
+            "a LUT6 has 6 inputs, but the function has {num_vars} variables"
+        )));
+    }
+    if init_str.len() > 16 || !init_str.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err(invalid_verilog(format!(
+            "invalid LUT6 INIT value '{init_str}'"
+        )));
+    }
+    check_verilog_names(num_vars, module_name, var_names)?;
+    let mut body =
+        format!("    LUT6 #(\n        .INIT(64'h{init_str:0>16})\n    ) lut (\n        .O(y)");
+    for i in 0..6 {
+        let input = if i < num_vars {
+            var_name_in(i, var_names)
+        } else {
+            "1'b0".into()
+        };
+        body = format!("{body},\n        .I{i}({input})");
+    }
+    body = format!("{body}\n    );\n");
+    Ok(verilog_module(num_vars, module_name, var_names, &body))
+}
+
+// Assignment of the output to the expression `expr` from `string_for_sop_minterms`
+// or `string_for_pos_maxterms`, which use the same operators as Verilog.
+fn verilog_assign(expr: &str) -> String {
+    let value = match expr {
+        "True" => "1'b1",
+        "False" => "1'b0",
+        _ => expr,
+    };
+    format!("    assign y = {value};\n")
+}
+
+// Check that the module and its input ports for `num_vars` variables
+// have Verilog identifiers as names, with no two ports named alike.
+fn check_verilog_names(
+    num_vars: usize,
+    module_name: &str,
+    var_names: &[String],
+) -> Result<(), QmError> {
+    if !is_verilog_identifier(module_name) {
+        return Err(invalid_verilog(format!(
+            "invalid Verilog module name '{module_name}'"
+        )));
+    }
+    if num_vars > MAX_VARS.max(var_names.len()) {
+        return Err(invalid_verilog(format!(
+            "no port names for {num_vars} variables"
+        )));
+    }
+    let port_names = (0..num_vars)
+        .map(|i| var_name_in(i, var_names))
+        .collect::<Vec<_>>();
+    for (i, var_name) in port_names.iter().enumerate() {
+        if !is_verilog_identifier(var_name) {
+            return Err(invalid_verilog(format!(
+                "invalid Verilog port name '{var_name}'"
+            )));
+        }
+        if port_names[..i].contains(var_name) {
+            return Err(invalid_verilog(format!(
+                "duplicate Verilog port name '{var_name}'"
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Invalid-input error for a Verilog module that can't be emitted.
+fn invalid_verilog(message: String) -> QmError {
+    QmError::InvalidInput(ParseError::new(0..0, message))
+}
+
+// Module with an input port for each variable and output port `y`,
+// containing the statements `body`.
+fn verilog_module(num_vars: usize, module_name: &str, var_names: &[String], body: &str) -> String {
+    let mut module_string = format!("module {module_name} (\n");
+    for i in 0..num_vars {
+        let var_name = var_name_in(i, var_names);
+        module_string = format!("{module_string}    input wire {var_name},\n");
+    }
+    format!("{module_string}    output wire y\n);\n{body}endmodule\n")
+}
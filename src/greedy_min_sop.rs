@@ -2,22 +2,54 @@
 //! an approximately-minimal sum-of-products from the prime
 //! implicant chart.
 //!
-//! Provides a much faster alternative to Petrick's method.
+//! Provides a much faster alternative to Petrick's method, along with an
+//! improved greedy cover that tries several randomized covers.
 
-use crate::{Minterm, PrimeImplicateChart, petrick::remove_essential_prime_impls};
+use std::cmp::Reverse;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    Minterm, PrimeImplicateChart,
+    cost::{CostModel, ProductCount, cover_cost, cover_key},
+    error::QmError,
+    petrick::remove_essential_prime_impls,
+    progress::{Monitor, Progress},
+};
 
 const EXTRA_DEBUG: bool = false;
 
+/// Restarts of the improved greedy cover pick at random among the rows
+/// covering at least this fraction, as `(numerator, denominator)`, of the
+/// uncovered columns per unit cost of the best row.
+const RANDOM_ROW_FRACTION: (u128, u128) = (3, 4);
+
 pub fn get_minimal_sops(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+) -> Result<Vec<Minterm>, QmError> {
+    get_minimal_sops_with_cost(prime_impl_chart, prime_impls, &ProductCount)
+}
+
+/// Like `get_minimal_sops`, but at each step selecting the prime implicant
+/// covering the most remaining columns per unit of cost according to `cost`.
+pub fn get_minimal_sops_with_cost(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+) -> Result<Vec<Minterm>, QmError> {
+    get_minimal_sops_with_monitor(prime_impl_chart, prime_impls, cost, &Monitor::NONE)
+}
+
+/// Like `get_minimal_sops_with_cost`, reporting each selection to `monitor`,
+/// and stopping if it is cancelled.
+pub fn get_minimal_sops_with_monitor(
     mut prime_impl_chart: PrimeImplicateChart,
     mut prime_impls: Vec<Minterm>,
-) -> Vec<Minterm> {
-    if prime_impl_chart.rows.is_empty() || prime_impl_chart.rows.first().unwrap().is_empty() {
-        // Ok to panic here because this condition indicates programmer error.
-        panic!("Prime implicant chart has either no rows or no columns.");
-    }
-    assert!(prime_impls.len() == prime_impl_chart.rows.len());
-    assert!(prime_impl_chart.rows.first().unwrap().len() <= 64);
+    cost: &dyn CostModel,
+    monitor: &Monitor,
+) -> Result<Vec<Minterm>, QmError> {
+    prime_impl_chart.check_shape(&prime_impls)?;
 
     if EXTRA_DEBUG {
         println!("PI chart before removing essential:\n{prime_impl_chart:?}");
@@ -25,10 +57,14 @@ pub fn get_minimal_sops(
 
     // Remove essential prime implicants from chart.
     let (mut min_expr_terms, remaining_cols) =
-        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, None);
+        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, None)?;
+    monitor.report(Progress::EssentialPrimeImplicants {
+        num_essential: min_expr_terms.len(),
+        num_remaining_cols: remaining_cols.len(),
+    });
     if remaining_cols.is_empty() {
         // Indicates all prime impls were essential, so we're done.
-        return min_expr_terms;
+        return Ok(min_expr_terms);
     }
     assert!(!prime_impl_chart.rows.is_empty());
 
@@ -37,47 +73,265 @@ pub fn get_minimal_sops(
         println!("Number of essential PIs: {}", min_expr_terms.len());
     }
 
-    let mut covered = vec![true; prime_impl_chart.rows.first().unwrap().len()];
-    remaining_cols
-        .iter()
-        .for_each(|index| covered[*index] = false);
-    let mut selected_rows = vec![false; prime_impl_chart.rows.len()];
+    let mut selection = Selection::new(&prime_impl_chart, &remaining_cols);
+    let mut num_selected = 0;
 
     // Keep selecting next best until the cover is complete.
-    while has_uncovered(&covered) {
-        let unselected_inds = selected_rows
+    while selection.has_uncovered() {
+        monitor.check_cancelled()?;
+        let row = selection.best_row(&prime_impls, cost);
+        selection.select(row);
+        num_selected += 1;
+        monitor.report(Progress::GreedySelection {
+            num_selected,
+            num_uncovered: selection.num_uncovered(),
+        });
+    }
+
+    min_expr_terms.extend(selection.terms(&prime_impls));
+    Ok(min_expr_terms)
+}
+
+/// Options for the improved greedy cover of `get_improved_sops`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GreedyOptions {
+    /// Number of randomized covers to try after the first.
+    pub restarts: usize,
+    /// Seed for the random choices of the restarts.
+    pub seed: u64,
+}
+
+impl Default for GreedyOptions {
+    fn default() -> Self {
+        GreedyOptions {
+            restarts: 32,
+            seed: 0,
+        }
+    }
+}
+
+/// Get an approximately-minimal cover with a stronger greedy heuristic.
+/// Each cover first selects any row that is the last one left covering
+/// some column, and otherwise the row covering the most remaining columns
+/// per unit of `cost`. The first cover takes the best row at each step,
+/// and each restart picks at random among the rows nearly as good as it.
+/// Rows made redundant by later selections are then removed from each
+/// cover, and the one of least cost is returned.
+pub fn get_improved_sops(
+    mut prime_impl_chart: PrimeImplicateChart,
+    mut prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    options: &GreedyOptions,
+    monitor: &Monitor,
+) -> Result<Vec<Minterm>, QmError> {
+    prime_impl_chart.check_shape(&prime_impls)?;
+
+    // Remove essential prime implicants from chart.
+    let (mut min_expr_terms, remaining_cols) =
+        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, None)?;
+    monitor.report(Progress::EssentialPrimeImplicants {
+        num_essential: min_expr_terms.len(),
+        num_remaining_cols: remaining_cols.len(),
+    });
+    if remaining_cols.is_empty() {
+        // Indicates all prime impls were essential, so we're done.
+        return Ok(min_expr_terms);
+    }
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut best_terms: Option<Vec<Minterm>> = None;
+    for restart in 0..=options.restarts {
+        let mut selection = Selection::new(&prime_impl_chart, &remaining_cols);
+        while selection.has_uncovered() {
+            monitor.check_cancelled()?;
+            let row = match selection.forced_row() {
+                Some(row) => row,
+                None if restart == 0 => selection.best_row(&prime_impls, cost),
+                None => selection.random_good_row(&prime_impls, cost, &mut rng),
+            };
+            selection.select(row);
+        }
+        selection.remove_redundant(&prime_impls, cost);
+
+        let terms = selection.terms(&prime_impls);
+        if best_terms
+            .as_ref()
+            .is_none_or(|best| cover_key(cost, &terms) < cover_key(cost, best))
+        {
+            best_terms = Some(terms);
+        }
+        monitor.report(Progress::GreedyRestart {
+            restart,
+            num_restarts: options.restarts,
+            best_cost: best_terms.as_ref().map_or(0, |best| cover_cost(cost, best)),
+        });
+    }
+
+    min_expr_terms.extend(best_terms.unwrap());
+    Ok(min_expr_terms)
+}
+
+/// Rows selected so far for a cover of the remaining columns of a chart.
+struct Selection<'a> {
+    chart: &'a PrimeImplicateChart,
+    remaining_cols: &'a [usize],
+    covered: Vec<bool>,
+    selected_rows: Vec<bool>,
+}
+
+impl<'a> Selection<'a> {
+    // An empty selection, where only `remaining_cols` need to be covered.
+    fn new(chart: &'a PrimeImplicateChart, remaining_cols: &'a [usize]) -> Self {
+        let mut covered = vec![true; chart.rows.first().unwrap().len()];
+        remaining_cols
             .iter()
-            .enumerate()
-            .filter_map(|(i, row_was_selected)| if !*row_was_selected { Some(i) } else { None })
-            .collect::<Vec<_>>();
-        assert!(!unselected_inds.is_empty());
+            .for_each(|index| covered[*index] = false);
+        Selection {
+            chart,
+            remaining_cols,
+            covered,
+            selected_rows: vec![false; chart.rows.len()],
+        }
+    }
 
-        let first = *unselected_inds.first().unwrap();
-        let mut max_count = count_additional_covered(&covered, &prime_impl_chart.rows[first]);
-        let mut max_row = first;
+    fn has_uncovered(&self) -> bool {
+        has_uncovered(&self.covered)
+    }
+
+    fn num_uncovered(&self) -> usize {
+        self.covered
+            .iter()
+            .filter(|is_covered| !**is_covered)
+            .count()
+    }
+
+    fn unselected_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.selected_rows.len()).filter(|row| !self.selected_rows[*row])
+    }
+
+    fn additional_covered(&self, row: usize) -> usize {
+        count_additional_covered(&self.covered, &self.chart.rows[row])
+    }
 
-        // Find unselected row with max coverage of uncovered cols.
-        for next in unselected_inds.iter().skip(1) {
-            let next_count = count_additional_covered(&covered, &prime_impl_chart.rows[*next]);
-            if next_count > max_count {
+    // The first unselected row with max coverage of uncovered cols per unit cost.
+    fn best_row(&self, prime_impls: &[Minterm], cost: &dyn CostModel) -> usize {
+        let mut rows = self.unselected_rows();
+        let first = rows.next().unwrap();
+        let mut max_count = self.additional_covered(first);
+        let mut max_row = first;
+        for next in rows {
+            let next_count = self.additional_covered(next);
+            if is_better_row(
+                (next_count, &prime_impls[next]),
+                (max_count, &prime_impls[max_row]),
+                cost,
+            ) {
                 max_count = next_count;
-                max_row = *next;
+                max_row = next;
             }
         }
-        selected_rows[max_row] = true;
-        for (i, col_in_row) in prime_impl_chart.rows[max_row].iter().enumerate() {
+        max_row
+    }
+
+    // A random row covering at least `RANDOM_ROW_FRACTION` as many uncovered
+    // columns per unit cost as the best row.
+    fn random_good_row(
+        &self,
+        prime_impls: &[Minterm],
+        cost: &dyn CostModel,
+        rng: &mut StdRng,
+    ) -> usize {
+        let best = self.best_row(prime_impls, cost);
+        let best_count = self.additional_covered(best) as u128;
+        let best_cost = cost.term_cost(&prime_impls[best]) as u128;
+        let (num, denom) = RANDOM_ROW_FRACTION;
+        let good_rows = self
+            .unselected_rows()
+            .filter(|row| {
+                let count = self.additional_covered(*row) as u128;
+                let row_cost = cost.term_cost(&prime_impls[*row]) as u128;
+                count > 0 && denom * count * best_cost >= num * best_count * row_cost
+            })
+            .collect::<Vec<_>>();
+        good_rows[rng.random_range(0..good_rows.len())]
+    }
+
+    // An unselected row that is the only one left covering some uncovered column.
+    fn forced_row(&self) -> Option<usize> {
+        (0..self.covered.len())
+            .filter(|col| !self.covered[*col])
+            .find_map(|col| {
+                let mut rows = self
+                    .unselected_rows()
+                    .filter(|row| self.chart.rows[*row][col]);
+                match (rows.next(), rows.next()) {
+                    (Some(row), None) => Some(row),
+                    _ => None,
+                }
+            })
+    }
+
+    fn select(&mut self, row: usize) {
+        self.selected_rows[row] = true;
+        for (i, col_in_row) in self.chart.rows[row].iter().enumerate() {
             if *col_in_row {
-                covered[i] = true;
+                self.covered[i] = true;
             }
         }
     }
 
-    for (row, selected) in selected_rows.iter().enumerate() {
-        if *selected {
-            min_expr_terms.push(prime_impls.get(row).unwrap().clone());
+    // Leave out selected rows whose columns are all covered by other selected
+    // rows, trying the most costly rows first.
+    fn remove_redundant(&mut self, prime_impls: &[Minterm], cost: &dyn CostModel) {
+        let mut num_covering = vec![0; self.covered.len()];
+        for (row, chart_row) in self.chart.rows.iter().enumerate() {
+            if self.selected_rows[row] {
+                for col in self.remaining_cols {
+                    num_covering[*col] += usize::from(chart_row[*col]);
+                }
+            }
+        }
+        let mut rows = (0..self.selected_rows.len())
+            .filter(|row| self.selected_rows[*row])
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| {
+            let term = &prime_impls[*row];
+            Reverse((cost.term_cost(term), term.num_literals()))
+        });
+        for row in rows {
+            let chart_row = &self.chart.rows[row];
+            let is_redundant = self
+                .remaining_cols
+                .iter()
+                .all(|col| !chart_row[*col] || num_covering[*col] > 1);
+            if is_redundant {
+                self.selected_rows[row] = false;
+                for col in self.remaining_cols {
+                    num_covering[*col] -= usize::from(chart_row[*col]);
+                }
+            }
         }
     }
-    min_expr_terms
+
+    fn terms(&self, prime_impls: &[Minterm]) -> Vec<Minterm> {
+        (0..self.selected_rows.len())
+            .filter(|row| self.selected_rows[*row])
+            .map(|row| prime_impls[row])
+            .collect()
+    }
+}
+
+// Compare rows by the number of uncovered columns they cover per unit of
+// cost, then by the number of columns, and then by fewest literals.
+fn is_better_row(
+    (count, term): (usize, &Minterm),
+    (other_count, other_term): (usize, &Minterm),
+    cost: &dyn CostModel,
+) -> bool {
+    // Compare the ratios of count to cost by cross-multiplying.
+    let ratio = count as u128 * cost.term_cost(other_term) as u128;
+    let other_ratio = other_count as u128 * cost.term_cost(term) as u128;
+    (ratio, count, other_term.num_literals()) > (other_ratio, other_count, term.num_literals())
 }
 
 // Count number of uncovered elements that would be covered
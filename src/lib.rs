@@ -1,89 +1,384 @@
 // Implement Quine-McCluskey.
 
+pub mod branch_bound;
 pub mod convert;
+pub mod cost;
+pub mod error;
+pub mod espresso;
+pub mod expr;
 pub mod format;
 pub mod greedy_min_sop;
+pub mod minimizer;
+pub mod multi_output;
 pub mod petrick;
+pub mod petrick_zdd;
+pub mod pla;
+pub mod progress;
+pub mod sat;
+pub mod sat_cover;
+pub mod solver;
 pub mod test;
 
-use std::{collections::HashSet, error::Error};
+use std::{collections::HashSet, ops::Range};
 
 use crate::{
-    convert::binary_strings_from_init_hex,
-    format::{display_sort_minterms, string_for_sop_minterms},
+    convert::{
+        binary_strings_from_init_hex, init_to_minterms, init_to_minterms_with_vars,
+        init_to_off_minterms, num_vars_for_init,
+    },
+    cost::ProductCount,
+    error::QmError,
+    format::{display_sort_minterms, string_for_pos_maxterms, string_for_sop_minterms},
+    minimizer::Minimizer,
     petrick::PetrickTimeInfo,
+    progress::{Monitor, Progress},
+    solver::{CoverSolution, CoverSolver, Greedy, Petrick, SolverStats},
 };
 
 // ------------------------
 // Top-level API functions.
 
-pub fn qm_simplify(minterms: &[Minterm]) -> (String, usize, PetrickTimeInfo) {
-    let prime_impls: Vec<Minterm> = get_prime_implicants(minterms).into_iter().collect();
-    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, minterms);
-    let (mut minimal_sops, time) = petrick::get_minimal_sop_terms(prime_impl_chart, prime_impls);
-    display_sort_minterms(&mut minimal_sops);
-    let message = string_for_sop_minterms(&minimal_sops, true, Some(" "));
-    (message, minimal_sops.len(), time)
+pub fn qm_simplify(minterms: &[Minterm]) -> Result<(String, usize, PetrickTimeInfo), QmError> {
+    qm_simplify_with_dont_cares(minterms, &[])
 }
 
-pub fn qm_simplify_init(
-    init_str: &str,
-) -> Result<(String, usize, PetrickTimeInfo), Box<dyn Error>> {
+/// Simplify the function with ON-set `minterms`, which may take any
+/// value on the don't-care set `dont_cares`.
+pub fn qm_simplify_with_dont_cares(
+    minterms: &[Minterm],
+    dont_cares: &[Minterm],
+) -> Result<(String, usize, PetrickTimeInfo), QmError> {
+    let (message, num_terms, stats) =
+        qm_simplify_with_solver(minterms, dont_cares, &Petrick::EXACT)?;
+    Ok((message, num_terms, stats.petrick_time.unwrap_or_default()))
+}
+
+/// Simplify the function with ON-set `minterms` and don't-care set
+/// `dont_cares`, using `solver` to choose a cover of the prime implicants.
+pub fn qm_simplify_with_solver(
+    minterms: &[Minterm],
+    dont_cares: &[Minterm],
+    solver: &dyn CoverSolver,
+) -> Result<(String, usize, SolverStats), QmError> {
+    let minimization = Minimizer::new().solver(solver).run(minterms, dont_cares)?;
+    let message = minimization.expr_string();
+    Ok((message, minimization.cover.len(), minimization.stats))
+}
+
+pub fn qm_simplify_init(init_str: &str) -> Result<(String, usize, PetrickTimeInfo), QmError> {
     let term_strings = binary_strings_from_init_hex(init_str)?;
     let minterms = term_strings
         .iter()
         .map(|s| (&**s).into())
         .collect::<Vec<_>>();
-    Ok(qm_simplify(&minterms))
+    qm_simplify(&minterms)
 }
 
-pub fn qm_simplify_greedy(minterms: &[Minterm]) -> (String, usize) {
-    let prime_impls: Vec<Minterm> = get_prime_implicants(minterms).into_iter().collect();
-    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, minterms);
-    let mut minimal_sops = greedy_min_sop::get_minimal_sops(prime_impl_chart, prime_impls);
-    display_sort_minterms(&mut minimal_sops);
-    (
-        string_for_sop_minterms(&minimal_sops, true, Some(" ")),
-        minimal_sops.len(),
-    )
+/// Simplify the function given by hex init string `init_str`, with
+/// don't-care input combinations given by the mask `dc_init_str`.
+pub fn qm_simplify_init_with_dont_cares(
+    init_str: &str,
+    dc_init_str: &str,
+) -> Result<(String, usize, PetrickTimeInfo), QmError> {
+    let num_vars = num_vars_for_init(init_str).max(num_vars_for_init(dc_init_str));
+    let minterms = init_to_minterms_with_vars(init_str, num_vars)?;
+    let dont_cares = init_to_minterms_with_vars(dc_init_str, num_vars)?;
+    qm_simplify_with_dont_cares(&minterms, &dont_cares)
 }
 
-pub fn qm_simplify_init_greedy(init_str: &str) -> Result<(String, usize), Box<dyn Error>> {
+pub fn qm_simplify_greedy(minterms: &[Minterm]) -> Result<(String, usize), QmError> {
+    qm_simplify_greedy_with_dont_cares(minterms, &[])
+}
+
+/// Like `qm_simplify_with_dont_cares`, but using the greedy cover.
+pub fn qm_simplify_greedy_with_dont_cares(
+    minterms: &[Minterm],
+    dont_cares: &[Minterm],
+) -> Result<(String, usize), QmError> {
+    let (message, num_terms, _) = qm_simplify_with_solver(minterms, dont_cares, &Greedy)?;
+    Ok((message, num_terms))
+}
+
+pub fn qm_simplify_init_greedy(init_str: &str) -> Result<(String, usize), QmError> {
     let term_strings = binary_strings_from_init_hex(init_str)?;
     let minterms = term_strings
         .iter()
         .map(|s| (&**s).into())
         .collect::<Vec<_>>();
-    Ok(qm_simplify_greedy(&minterms))
+    qm_simplify_greedy(&minterms)
+}
+
+/// Get all minimal sum-of-products for the function with ON-set `minterms`,
+/// up to `max_covers` of them if given, along with the number that exist.
+/// The strings are sorted, with each in the same form as from `qm_simplify`.
+pub fn qm_simplify_all(
+    minterms: &[Minterm],
+    max_covers: Option<usize>,
+) -> Result<(Vec<String>, usize), QmError> {
+    if minterms.is_empty() {
+        return Ok((vec!["False".into()], 1));
+    }
+    let prime_impls: Vec<Minterm> = get_prime_implicants(minterms)?.into_iter().collect();
+    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, minterms)?;
+    let (covers, num_covers) = petrick::get_all_minimal_sop_terms_with_cost(
+        prime_impl_chart,
+        prime_impls,
+        &ProductCount,
+        None,
+    )?;
+
+    let mut sop_strings = covers
+        .into_iter()
+        .map(|mut terms| {
+            display_sort_minterms(&mut terms);
+            string_for_sop_minterms(&terms, true, Some(" "))
+        })
+        .collect::<Vec<_>>();
+    sop_strings.sort();
+    sop_strings.truncate(max_covers.unwrap_or(usize::MAX));
+    Ok((sop_strings, num_covers))
+}
+
+pub fn qm_simplify_init_all(
+    init_str: &str,
+    max_covers: Option<usize>,
+) -> Result<(Vec<String>, usize), QmError> {
+    let minterms = init_to_minterms(init_str)?;
+    qm_simplify_all(&minterms, max_covers)
+}
+
+/// Get a minimal product-of-sums for the function with OFF-set `off_minterms`,
+/// by finding a minimal sum-of-products for its complement. The string
+/// returned has the form `(A | ~B) & (C)`.
+pub fn qm_simplify_pos(
+    off_minterms: &[Minterm],
+) -> Result<(String, usize, PetrickTimeInfo), QmError> {
+    qm_simplify_pos_with_dont_cares(off_minterms, &[])
+}
+
+/// Like `qm_simplify_pos`, but with don't-care set `dont_cares`.
+pub fn qm_simplify_pos_with_dont_cares(
+    off_minterms: &[Minterm],
+    dont_cares: &[Minterm],
+) -> Result<(String, usize, PetrickTimeInfo), QmError> {
+    let (mut maxterms, time) = get_minimal_pos_maxterms(off_minterms, dont_cares)?;
+    display_sort_minterms(&mut maxterms);
+    let message = string_for_pos_maxterms(&maxterms, true, Some(" "));
+    Ok((message, maxterms.len(), time))
+}
+
+/// Get maxterms for a minimal product-of-sums of the function with OFF-set
+/// `off_minterms`. Each maxterm is stored as a `Minterm` listing the literals
+/// of the sum: `b'1'` for a variable, `b'0'` for its negation.
+pub fn get_minimal_pos_maxterms(
+    off_minterms: &[Minterm],
+    dont_cares: &[Minterm],
+) -> Result<(Vec<Minterm>, PetrickTimeInfo), QmError> {
+    let solution = get_minimal_cover(off_minterms, dont_cares, &Petrick::EXACT)?;
+    let maxterms = solution
+        .terms
+        .iter()
+        .map(Minterm::negated_literals)
+        .collect();
+    Ok((maxterms, solution.stats.petrick_time.unwrap_or_default()))
+}
+
+pub fn qm_simplify_init_pos(init_str: &str) -> Result<(String, usize, PetrickTimeInfo), QmError> {
+    let off_minterms = init_to_off_minterms(init_str)?;
+    qm_simplify_pos(&off_minterms)
+}
+
+/// Simplify the functions given by hex init strings `init_strs` together, sharing
+/// product terms between them. Returns a sum-of-products string for each function
+/// and the total number of distinct products used.
+pub fn qm_simplify_multi_init(init_strs: &[&str]) -> Result<(Vec<String>, usize), QmError> {
+    let num_vars = init_strs
+        .iter()
+        .map(|s| num_vars_for_init(s))
+        .max()
+        .unwrap_or_default();
+    let functions = init_strs
+        .iter()
+        .map(|s| init_to_minterms_with_vars(s, num_vars))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let cover = multi_output::get_minimal_multi_output_cover(&functions, &Petrick::EXACT)?;
+    let sop_strings = cover
+        .outputs
+        .into_iter()
+        .map(|mut terms| {
+            display_sort_minterms(&mut terms);
+            string_for_sop_minterms(&terms, true, Some(" "))
+        })
+        .collect();
+    Ok((sop_strings, cover.products.len()))
+}
+
+// Get a minimal cover of the ON-set `minterms` by prime implicants, using
+// `solver` to choose the cover. An empty ON-set has the empty cover.
+fn get_minimal_cover(
+    minterms: &[Minterm],
+    dont_cares: &[Minterm],
+    solver: &dyn CoverSolver,
+) -> Result<CoverSolution, QmError> {
+    if minterms.is_empty() {
+        return Ok(CoverSolution {
+            terms: vec![],
+            stats: SolverStats {
+                is_minimal: true,
+                ..Default::default()
+            },
+        });
+    }
+    let prime_impls: Vec<Minterm> = get_prime_implicants_with_dont_cares(minterms, dont_cares)?
+        .into_iter()
+        .collect();
+    let prime_impl_chart = create_prime_implicant_chart(&prime_impls, minterms)?;
+    solver.solve(prime_impl_chart, prime_impls, &ProductCount, &Monitor::NONE)
 }
 
 // ------------------
 // Minterm structure.
 
-#[derive(Hash, Clone, PartialEq, Eq)]
+/// Maximum number of variables a function can have. This is limited
+/// by our use of single letters `A`-`Z` for variable names.
+pub const MAX_VARS: usize = 26;
+
+/// A product term over some number of variables, stored as bitmasks with
+/// bit `i` for variable `i` (so bit 0 is variable `A`). Variables without
+/// a `care` bit are "don't care", i.e. have no literal in the product.
+///
+/// As strings, each position holds one of `0`, `1` or `x`, with the _last_
+/// position corresponding to variable `A`, the second-to-last to `B`, etc.
+#[derive(Hash, Clone, Copy, PartialEq, Eq)]
 pub struct Minterm {
-    values: Vec<u8>,
+    /// Values of the variables with literals; zero for the others.
+    value: u32,
+    /// Bit `i` is set if variable `i` has a literal in the product.
+    care: u32,
+    num_vars: u8,
 }
 
 impl Minterm {
-    pub fn merge(&self, other: &Minterm, first_diff: usize) -> Minterm {
-        let mut outterm = other.clone();
-        outterm.values[first_diff] = b'x';
-        outterm
+    /// The product term with no literals, over `num_vars` variables.
+    pub fn new_true(num_vars: usize) -> Minterm {
+        assert!(num_vars <= MAX_VARS);
+        Minterm {
+            value: 0,
+            care: 0,
+            num_vars: num_vars as u8,
+        }
+    }
+
+    /// The minterm for input combination `index` of a function of `num_vars`
+    /// variables, where bit `i` of the index is the value of variable `i`.
+    pub fn from_index(index: usize, num_vars: usize) -> Minterm {
+        assert!(num_vars <= MAX_VARS && index < 1 << num_vars);
+        Minterm {
+            value: index as u32,
+            care: Self::vars_mask(num_vars),
+            num_vars: num_vars as u8,
+        }
+    }
+
+    /// Number of variables of the function this term belongs to.
+    pub fn num_vars(&self) -> usize {
+        self.num_vars as usize
+    }
+
+    /// Number of literals in the product.
+    pub fn num_literals(&self) -> usize {
+        self.care.count_ones() as usize
+    }
+
+    /// The literal for variable `var` in this term: `Some(true)` for
+    /// the variable, `Some(false)` for its negation, or `None` if the
+    /// variable does not appear.
+    pub fn literal(&self, var: usize) -> Option<bool> {
+        assert!(var < self.num_vars());
+        (self.care & (1 << var) != 0).then_some(self.value & (1 << var) != 0)
+    }
+
+    /// Set the literal for variable `var` in this term, as for `literal`.
+    pub fn set_literal(&mut self, var: usize, literal: Option<bool>) {
+        assert!(var < self.num_vars());
+        let bit = 1 << var;
+        self.value &= !bit;
+        self.care &= !bit;
+        if let Some(value) = literal {
+            self.care |= bit;
+            if value {
+                self.value |= bit;
+            }
+        }
+    }
+
+    /// Swap the positive and negative literals of this term. This converts
+    /// an implicant of the complement of a function to a maxterm (i.e. sum
+    /// term) of the function itself, and vice-versa; see `qm_simplify_pos`.
+    pub fn negated_literals(&self) -> Minterm {
+        Minterm {
+            value: self.value ^ self.care,
+            ..*self
+        }
+    }
+
+    /// Merge with a term that differs only in the value of variable `var`.
+    pub fn merge(&self, other: &Minterm, var: usize) -> Minterm {
+        let care = other.care & !(1 << var);
+        Minterm {
+            value: other.value & care,
+            care,
+            num_vars: other.num_vars,
+        }
+    }
+
+    /// Indices of the input combinations covered by this term.
+    pub fn covered_indices(&self) -> impl Iterator<Item = usize> {
+        // Step through the subsets of the don't-care variables.
+        let free = Self::vars_mask(self.num_vars()) & !self.care;
+        let value = self.value;
+        let mut subset = Some(0_u32);
+        std::iter::from_fn(move || {
+            let current = subset?;
+            subset = (current != free).then(|| current.wrapping_sub(free) & free);
+            Some((value | current) as usize)
+        })
+    }
+
+    // Mask with a bit for each of `num_vars` variables.
+    fn vars_mask(num_vars: usize) -> u32 {
+        ((1_u64 << num_vars) - 1) as u32
     }
 }
 
 impl std::fmt::Debug for Minterm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Minterm: {}", std::str::from_utf8(&self.values).unwrap())
+        let values = (0..self.num_vars())
+            .rev()
+            .map(|var| match self.literal(var) {
+                Some(true) => '1',
+                Some(false) => '0',
+                None => 'x',
+            })
+            .collect::<String>();
+        write!(f, "Minterm: {values}")
     }
 }
 
 impl From<&str> for Minterm {
     fn from(values: &str) -> Self {
-        Minterm {
-            values: values.into(),
+        let mut minterm = Minterm::new_true(values.len());
+        for (var, value) in values.bytes().rev().enumerate() {
+            let literal = match value {
+                b'0' => Some(false),
+                b'1' => Some(true),
+                b'x' => None,
+                _ => panic!("invalid minterm value '{}'", value as char),
+            };
+            minterm.set_literal(var, literal);
         }
+        minterm
     }
 }
 
@@ -92,75 +387,176 @@ impl From<&str> for Minterm {
 
 const DEV_DEBUG: bool = false;
 
-pub fn get_prime_implicants(minterms: &[Minterm]) -> HashSet<Minterm> {
+pub fn get_prime_implicants(minterms: &[Minterm]) -> Result<HashSet<Minterm>, QmError> {
+    get_prime_implicants_with_monitor(minterms, &[], &Monitor::NONE)
+}
+
+/// Represents a range of terms with the same number of 1's,
+/// in a column of terms sorted by number of 1's.
+struct OnesGroup {
+    n_ones: u32,
+    range: Range<usize>,
+}
+
+// Merge implicants with the tabular method, until only prime implicants remain.
+// Column `k` of the table holds the terms with `k` don't-cares, from merging
+// pairs of terms in the previous column or from the input (which may contain
+// products as well as minterms). Terms that don't merge with any other are prime.
+fn merge_implicants(minterms: &[Minterm], monitor: &Monitor) -> Result<HashSet<Minterm>, QmError> {
     let mut prime_implicants = HashSet::<Minterm>::new();
-    let mut was_merged = vec![false; minterms.len()];
-
-    for i in 0..minterms.len() {
-        for j in i + 1..minterms.len() {
-            let minterm_i = &minterms[i];
-            let minterm_j = &minterms[j];
-            if let Some(n) = can_merge(minterm_i, minterm_j) {
-                prime_implicants.insert(minterm_i.merge(minterm_j, n));
-                was_merged[i] = true;
-                was_merged[j] = true;
+    let Some(num_vars) = minterms.first().map(Minterm::num_vars) else {
+        return Ok(prime_implicants);
+    };
+    let mut input_columns = vec![vec![]; num_vars + 1];
+    for minterm in minterms {
+        input_columns[num_vars - minterm.care.count_ones() as usize].push(*minterm);
+    }
+
+    let mut column = vec![];
+    for (level, input_column) in input_columns.into_iter().enumerate() {
+        monitor.check_cancelled()?;
+        column.extend(input_column);
+        let ones_groups = sort_into_ones_groups(&mut column);
+        monitor.report(Progress::PrimeImplicants {
+            level,
+            num_terms: column.len(),
+        });
+        let mut was_merged = vec![false; column.len()];
+        let mut next_column = vec![];
+
+        // Terms that can merge differ in one value, so are in adjacent groups.
+        for (group, next_group) in ones_groups.iter().zip(ones_groups.iter().skip(1)) {
+            if next_group.n_ones != group.n_ones + 1 {
+                continue;
+            }
+            let next_terms = &column[next_group.range.clone()];
+            for i in group.range.clone() {
+                // Groups are sorted by care mask, which must match to merge.
+                let care = column[i].care;
+                let start = next_terms.partition_point(|term| term.care < care);
+                for (j, term_j) in next_terms.iter().enumerate().skip(start) {
+                    if term_j.care != care {
+                        break;
+                    }
+                    if let Some(n) = can_merge(&column[i], term_j) {
+                        next_column.push(column[i].merge(term_j, n));
+                        was_merged[i] = true;
+                        was_merged[next_group.range.start + j] = true;
+                    }
+                }
+            }
+        }
+        for (i, was) in was_merged.iter().enumerate() {
+            if !was {
+                prime_implicants.insert(column[i]);
             }
         }
+
+        if DEV_DEBUG {
+            // Print intermediate results for debugging.
+            let mut current_terms = next_column.clone();
+            display_sort_minterms(&mut current_terms);
+            println!(
+                "\nAfter merge operation:\n  {}",
+                string_for_sop_minterms(&current_terms, false, Some("\n"))
+            );
+        }
+        column = next_column;
     }
-    for (i, was) in was_merged.iter().enumerate() {
-        if !was {
-            prime_implicants.insert(minterms[i].clone());
+
+    Ok(prime_implicants)
+}
+
+// Sort and deduplicate a column of terms, grouping them by number of
+// 1's and then by care mask, and return the groups.
+fn sort_into_ones_groups(column: &mut Vec<Minterm>) -> Vec<OnesGroup> {
+    column.sort_by_key(ones_group_order);
+    column.dedup();
+    ones_groups(column.iter())
+}
+
+// Order of the terms in a column: by number of 1's, then by care mask.
+fn ones_group_order(term: &Minterm) -> (u32, u32, u32) {
+    (term.value.count_ones(), term.care, term.value)
+}
+
+// Get the groups of a column of terms sorted by `ones_group_order`.
+fn ones_groups<'a>(column: impl IntoIterator<Item = &'a Minterm>) -> Vec<OnesGroup> {
+    let mut ones_groups: Vec<OnesGroup> = vec![];
+    for (i, term) in column.into_iter().enumerate() {
+        let n_ones = term.value.count_ones();
+        match ones_groups.last_mut() {
+            Some(group) if group.n_ones == n_ones => group.range.end = i + 1,
+            _ => ones_groups.push(OnesGroup {
+                n_ones,
+                range: i..i + 1,
+            }),
         }
     }
+    ones_groups
+}
 
-    if DEV_DEBUG {
-        // Print intermediate results for debugging.
-        let mut current_terms = prime_implicants.iter().cloned().collect::<Vec<Minterm>>();
-        display_sort_minterms(&mut current_terms);
-        println!(
-            "\nAfter merge operation:\n  {}",
-            string_for_sop_minterms(&current_terms, false, Some("\n"))
-        );
+/// Get the prime implicants of a function with ON-set `minterms` and
+/// don't-care set `dont_cares`. Terms may merge through don't-cares, but
+/// prime implicants covering only don't-cares are left out, since they
+/// are never needed in a cover of the ON-set.
+pub fn get_prime_implicants_with_dont_cares(
+    minterms: &[Minterm],
+    dont_cares: &[Minterm],
+) -> Result<HashSet<Minterm>, QmError> {
+    get_prime_implicants_with_monitor(minterms, dont_cares, &Monitor::NONE)
+}
+
+/// Like `get_prime_implicants_with_dont_cares`, reporting progress for each
+/// column of the table to `monitor`, and stopping if it is cancelled.
+pub fn get_prime_implicants_with_monitor(
+    minterms: &[Minterm],
+    dont_cares: &[Minterm],
+    monitor: &Monitor,
+) -> Result<HashSet<Minterm>, QmError> {
+    if dont_cares.is_empty() {
+        check_num_vars(minterms)?;
+        return merge_implicants(minterms, monitor);
     }
+    let on_set: HashSet<&Minterm> = minterms.iter().collect();
+    let mut all_terms = minterms.to_vec();
+    all_terms.extend(dont_cares.iter().filter(|m| !on_set.contains(m)).cloned());
+    check_num_vars(&all_terms)?;
 
-    if was_merged.iter().filter(|w| **w).count() == 0 {
-        prime_implicants
-    } else {
-        get_prime_implicants(&prime_implicants.into_iter().collect::<Vec<Minterm>>())
+    Ok(merge_implicants(&all_terms, monitor)?
+        .into_iter()
+        .filter(|prime_impl| minterms.iter().any(|m| check_match(prime_impl, m)))
+        .collect())
+}
+
+/// Check that all of `terms` have the same number of variables.
+pub fn check_num_vars(terms: &[Minterm]) -> Result<(), QmError> {
+    let Some(expected) = terms.first().map(Minterm::num_vars) else {
+        return Ok(());
+    };
+    match terms.iter().find(|term| term.num_vars() != expected) {
+        Some(term) => Err(QmError::WidthMismatch {
+            expected,
+            found: term.num_vars(),
+        }),
+        None => Ok(()),
     }
 }
 
 /// Minterms can be merged if they differ in exactly one variable,
-/// with neither minterm having a "don't care" in that position.
+/// with neither minterm having a "don't care" for that variable.
+/// Returns the variable they differ in.
 fn can_merge(minterm_1: &Minterm, minterm_2: &Minterm) -> Option<usize> {
-    assert!(minterm_1.values.len() == minterm_2.values.len());
-    let mut first_diff = None;
-    for (i, (val_1, val_2)) in minterm_1
-        .values
-        .iter()
-        .zip(minterm_2.values.iter())
-        .enumerate()
-    {
-        match (val_1, val_2) {
-            (b'x', b'1' | b'0') => return None,
-            (b'1' | b'0', b'x') => return None,
-            (b'1', b'0') | (b'0', b'1') => {
-                if first_diff.is_none() {
-                    first_diff = Some(i);
-                } else {
-                    // More than one mismatch, so incompatible.
-                    return None;
-                }
-            }
-            _ => {}
-        }
-    }
-    first_diff
+    assert!(minterm_1.num_vars == minterm_2.num_vars);
+    let diff = minterm_1.value ^ minterm_2.value;
+    (minterm_1.care == minterm_2.care && diff.count_ones() == 1)
+        .then_some(diff.trailing_zeros() as usize)
 }
 
 // ---------------------------
 // Prime implicate chart type.
 
+#[derive(Clone)]
 pub struct PrimeImplicateChart {
     rows: Vec<Vec<bool>>,
 }
@@ -213,33 +609,52 @@ impl PrimeImplicateChart {
     fn count_in_row(&self, row: usize) -> usize {
         self.rows[row].iter().filter(|s| **s).count()
     }
+
+    /// Check that the chart is nonempty and has a row for each of `prime_impls`.
+    fn check_shape(&self, prime_impls: &[Minterm]) -> Result<(), QmError> {
+        if self.rows.is_empty() || self.rows.first().unwrap().is_empty() {
+            return Err(QmError::EmptyFunction);
+        }
+        if prime_impls.len() != self.rows.len() {
+            return Err(QmError::WidthMismatch {
+                expected: self.rows.len(),
+                found: prime_impls.len(),
+            });
+        }
+        Ok(())
+    }
 }
 
 pub fn create_prime_implicant_chart(
     prime_impls: &[Minterm],
     minterms: &[Minterm],
-) -> PrimeImplicateChart {
+) -> Result<PrimeImplicateChart, QmError> {
+    check_num_vars(prime_impls)?;
+    check_num_vars(minterms)?;
+    if let (Some(prime_impl), Some(minterm)) = (prime_impls.first(), minterms.first())
+        && prime_impl.num_vars() != minterm.num_vars()
+    {
+        return Err(QmError::WidthMismatch {
+            expected: minterm.num_vars(),
+            found: prime_impl.num_vars(),
+        });
+    }
+
     let mut prime_impl_chart = vec![vec![false; minterms.len()]; prime_impls.len()];
     for (i, row) in prime_impl_chart.iter_mut().enumerate() {
         set_matches(&prime_impls[i], minterms, row);
     }
 
-    PrimeImplicateChart {
+    Ok(PrimeImplicateChart {
         rows: prime_impl_chart,
-    }
+    })
 }
 
 fn check_match(minterm_1: &Minterm, minterm_2: &Minterm) -> bool {
-    assert!(minterm_1.values.len() == minterm_2.values.len());
-    for i in 0..minterm_1.values.len() {
-        if minterm_1.values[i] == b'x' {
-            continue;
-        }
-        if minterm_1.values[i] != minterm_2.values[i] {
-            return false;
-        }
-    }
-    true
+    assert!(minterm_1.num_vars == minterm_2.num_vars);
+    // Each literal of the first term must appear in the second.
+    minterm_1.care & !minterm_2.care == 0
+        && (minterm_1.value ^ minterm_2.value) & minterm_1.care == 0
 }
 
 fn set_matches(patt_term: &Minterm, minterms: &[Minterm], matches: &mut [bool]) {
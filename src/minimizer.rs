@@ -0,0 +1,290 @@
+//! A configurable interface to the whole minimization. A `Minimizer` holds
+//! the options -- solver, cost model, variable names, output form and time
+//! budget, as well as a cancellation token and progress callback -- and
+//! `Minimizer::run` returns a `Minimization` holding the terms from each
+//! stage, so that callers don't need to parse the result string.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Minterm,
+    convert::{complement_minterms, init_to_minterms_with_vars, num_vars_for_init},
+    cost::{CostModel, ProductCount, cover_cost},
+    create_prime_implicant_chart,
+    error::QmError,
+    format::{
+        display_sort_minterms, string_for_pos_maxterms_with_names,
+        string_for_sop_minterms_with_names,
+    },
+    get_prime_implicants_with_monitor,
+    progress::{CancelToken, Monitor, Progress},
+    solver::{CoverSolver, Petrick, SolverStats},
+};
+
+/// Form of the minimized expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputForm {
+    /// A sum of products, such as `(A & ~B) | (C)`.
+    #[default]
+    SumOfProducts,
+    /// A product of sums, such as `(A | ~B) & (C)`, found by minimizing
+    /// the OFF-set of the function.
+    ProductOfSums,
+}
+
+/// Builder for a minimization, e.g.
+/// `Minimizer::new().solver(&BranchBound).cost(&LiteralCount).run(&minterms, &[])`.
+pub struct Minimizer<'a> {
+    solver: &'a dyn CoverSolver,
+    cost: &'a dyn CostModel,
+    var_names: Vec<String>,
+    form: OutputForm,
+    time_budget: Option<Duration>,
+    monitor: Monitor<'a>,
+}
+
+impl Default for Minimizer<'_> {
+    /// A minimizer for a sum-of-products with the fewest products,
+    /// using Petrick's method.
+    fn default() -> Self {
+        Minimizer {
+            solver: &Petrick::EXACT,
+            cost: &ProductCount,
+            var_names: vec![],
+            form: OutputForm::SumOfProducts,
+            time_budget: None,
+            monitor: Monitor::NONE,
+        }
+    }
+}
+
+impl<'a> Minimizer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `solver` to choose a cover of the prime implicants.
+    pub fn solver(mut self, solver: &'a dyn CoverSolver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    /// Minimize the total cost of the cover according to `cost`.
+    pub fn cost(mut self, cost: &'a dyn CostModel) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Names for variables `A`, `B`, ... in the expression string. Variables
+    /// past the end of `var_names` keep their letters.
+    pub fn var_names<S: Into<String>>(mut self, var_names: impl IntoIterator<Item = S>) -> Self {
+        self.var_names = var_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn form(mut self, form: OutputForm) -> Self {
+        self.form = form;
+        self
+    }
+
+    /// Fail with `QmError::BudgetExceeded` if the minimization takes longer
+    /// than `time_budget`. The deadline is checked while generating prime
+    /// implicants and by the solvers, in the same places as cancellation.
+    pub fn time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Stop with `QmError::Cancelled` once `cancel` is cancelled.
+    pub fn cancel_token(mut self, cancel: &'a CancelToken) -> Self {
+        self.monitor = self.monitor.with_cancel(cancel);
+        self
+    }
+
+    /// Call `progress` with each phase of the minimization.
+    pub fn progress(mut self, progress: &'a (dyn Fn(Progress) + Sync)) -> Self {
+        self.monitor = self.monitor.with_progress(progress);
+        self
+    }
+
+    /// Minimize the function with ON-set `minterms`, which may take any
+    /// value on the don't-care set `dont_cares`.
+    pub fn run(
+        &self,
+        minterms: &[Minterm],
+        dont_cares: &[Minterm],
+    ) -> Result<Minimization, QmError> {
+        let num_vars = minterms
+            .iter()
+            .chain(dont_cares)
+            .map(Minterm::num_vars)
+            .next()
+            .unwrap_or_default();
+        self.run_with_vars(minterms, dont_cares, num_vars)
+    }
+
+    /// Minimize the function given by hex init string `init_str`.
+    pub fn run_init(&self, init_str: &str) -> Result<Minimization, QmError> {
+        let num_vars = num_vars_for_init(init_str);
+        let minterms = init_to_minterms_with_vars(init_str, num_vars)?;
+        self.run_with_vars(&minterms, &[], num_vars)
+    }
+
+    fn run_with_vars(
+        &self,
+        minterms: &[Minterm],
+        dont_cares: &[Minterm],
+        num_vars: usize,
+    ) -> Result<Minimization, QmError> {
+        let start = Instant::now();
+        let mut time = MinimizationTime::default();
+        let monitor = match self
+            .time_budget
+            .and_then(|budget| start.checked_add(budget))
+        {
+            Some(deadline) => self.monitor.with_deadline(deadline),
+            None => self.monitor,
+        };
+
+        // For a product-of-sums, cover the OFF-set instead.
+        let to_cover = match self.form {
+            OutputForm::SumOfProducts => minterms.to_vec(),
+            OutputForm::ProductOfSums => {
+                let on_set = minterms
+                    .iter()
+                    .chain(dont_cares)
+                    .copied()
+                    .collect::<Vec<_>>();
+                complement_minterms(&on_set, num_vars)
+            }
+        };
+
+        let (prime_impls, essential_prime_impls, cover, stats) = if to_cover.is_empty() {
+            let stats = SolverStats {
+                is_minimal: true,
+                ..Default::default()
+            };
+            (vec![], vec![], vec![], stats)
+        } else {
+            let stage_start = Instant::now();
+            let mut prime_impls: Vec<Minterm> =
+                get_prime_implicants_with_monitor(&to_cover, dont_cares, &monitor)?
+                    .into_iter()
+                    .collect();
+            display_sort_minterms(&mut prime_impls);
+            time.prime_implicants = stage_start.elapsed();
+            monitor.check_cancelled()?;
+
+            let stage_start = Instant::now();
+            let chart = create_prime_implicant_chart(&prime_impls, &to_cover)?;
+            // Essential prime implicants are the only row covering some column.
+            let mut is_essential = vec![false; prime_impls.len()];
+            for col in 0..to_cover.len() {
+                let mut rows = (0..prime_impls.len()).filter(|row| chart.rows[*row][col]);
+                if let (Some(row), None) = (rows.next(), rows.next()) {
+                    is_essential[row] = true;
+                }
+            }
+            let essential_prime_impls = prime_impls
+                .iter()
+                .zip(&is_essential)
+                .filter(|(_, is_essential)| **is_essential)
+                .map(|(term, _)| *term)
+                .collect();
+            time.chart = stage_start.elapsed();
+            monitor.check_cancelled()?;
+
+            let stage_start = Instant::now();
+            let solution = self
+                .solver
+                .solve(chart, prime_impls.clone(), self.cost, &monitor)?;
+            time.cover = stage_start.elapsed();
+            (
+                prime_impls,
+                essential_prime_impls,
+                solution.terms,
+                solution.stats,
+            )
+        };
+
+        // Store the terms of a product-of-sums as maxterms.
+        let as_output_terms = |mut terms: Vec<Minterm>| {
+            if self.form == OutputForm::ProductOfSums {
+                terms = terms.iter().map(Minterm::negated_literals).collect();
+            }
+            display_sort_minterms(&mut terms);
+            terms
+        };
+        let cost = cover_cost(self.cost, &cover);
+        time.total = start.elapsed();
+        Ok(Minimization {
+            num_vars,
+            minterms: minterms.to_vec(),
+            dont_cares: dont_cares.to_vec(),
+            prime_implicants: as_output_terms(prime_impls),
+            essential_prime_implicants: as_output_terms(essential_prime_impls),
+            cover: as_output_terms(cover),
+            cost,
+            form: self.form,
+            var_names: self.var_names.clone(),
+            stats,
+            time,
+        })
+    }
+}
+
+/// Time taken by each stage of a minimization.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MinimizationTime {
+    pub prime_implicants: Duration,
+    pub chart: Duration,
+    pub cover: Duration,
+    pub total: Duration,
+}
+
+/// The result of `Minimizer::run`. For `OutputForm::ProductOfSums` the prime
+/// implicants, essential prime implicants and cover are all maxterms, i.e.
+/// the prime implicants of the OFF-set with their literals negated.
+#[derive(Debug)]
+pub struct Minimization {
+    pub num_vars: usize,
+    /// The ON-set and don't-care set that were minimized.
+    pub minterms: Vec<Minterm>,
+    pub dont_cares: Vec<Minterm>,
+    pub prime_implicants: Vec<Minterm>,
+    /// Prime implicants that are the only one covering some minterm,
+    /// and so are part of every cover.
+    pub essential_prime_implicants: Vec<Minterm>,
+    /// The terms of the minimized expression, sorted for display.
+    pub cover: Vec<Minterm>,
+    /// Cost of the cover according to the minimizer's cost model.
+    pub cost: u64,
+    pub form: OutputForm,
+    pub var_names: Vec<String>,
+    pub stats: SolverStats,
+    pub time: MinimizationTime,
+}
+
+impl Minimization {
+    /// The minimized expression, in the same form as from `qm_simplify`
+    /// or `qm_simplify_pos`, but using the minimizer's variable names.
+    pub fn expr_string(&self) -> String {
+        match self.form {
+            OutputForm::SumOfProducts => {
+                string_for_sop_minterms_with_names(&self.cover, true, Some(" "), &self.var_names)
+            }
+            OutputForm::ProductOfSums => {
+                string_for_pos_maxterms_with_names(&self.cover, true, Some(" "), &self.var_names)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Minimization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr_string())
+    }
+}
@@ -0,0 +1,240 @@
+//! Minimize several functions of the same inputs together, so that
+//! product terms can be shared between the outputs.
+//!
+//! Each minterm is tagged with the set of outputs it belongs to, and
+//! merging two terms keeps only the outputs they have in common. The
+//! resulting multi-output prime implicants are the rows of a combined
+//! prime implicant chart, with a column for each (output, minterm) pair,
+//! so that a minimal cover of the chart uses the fewest distinct products.
+
+use std::collections::HashMap;
+
+use crate::{
+    Minterm, PrimeImplicateChart, can_merge, check_match, check_num_vars,
+    cost::ProductCount,
+    error::{ParseError, QmError},
+    ones_group_order, ones_groups,
+    progress::Monitor,
+    solver::CoverSolver,
+};
+
+/// Maximum number of outputs, limited by the size of the output tag.
+pub const MAX_OUTPUTS: usize = 64;
+
+const DEV_DEBUG: bool = false;
+
+// -------------------------
+// Tagged minterm structure.
+
+/// A product term tagged with the set of outputs it is an implicant of.
+#[derive(Hash, Clone, PartialEq, Eq)]
+pub struct TaggedMinterm {
+    pub term: Minterm,
+    /// Bit `i` is set if the term is an implicant of output `i`.
+    pub outputs: u64,
+}
+
+impl TaggedMinterm {
+    pub fn has_output(&self, output: usize) -> bool {
+        self.outputs & (1 << output) != 0
+    }
+}
+
+impl std::fmt::Debug for TaggedMinterm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} -> {:#b}", self.term, self.outputs)
+    }
+}
+
+/// Tag the minterms of each function in `functions` with the
+/// outputs they belong to, combining minterms shared by outputs.
+pub fn tag_minterms(functions: &[Vec<Minterm>]) -> Vec<TaggedMinterm> {
+    assert!(functions.len() <= MAX_OUTPUTS);
+    let mut tags = HashMap::<Minterm, u64>::new();
+    for (output, minterms) in functions.iter().enumerate() {
+        for minterm in minterms {
+            *tags.entry(*minterm).or_default() |= 1 << output;
+        }
+    }
+    tags.into_iter()
+        .map(|(term, outputs)| TaggedMinterm { term, outputs })
+        .collect()
+}
+
+// ---------------------------------------------------
+// Compute multi-output prime implicants from minterms.
+
+/// Get the multi-output prime implicants for a list of tagged minterms.
+///
+/// A tagged term is prime if no larger term is an implicant of all of its
+/// outputs. So, unlike in the single-output case, a term can be prime even
+/// though it merged with other terms, as long as each merge lost an output.
+pub fn get_multi_output_prime_implicants(minterms: &[TaggedMinterm]) -> Vec<TaggedMinterm> {
+    let mut prime_implicants = vec![];
+    let mut current_terms = minterms.to_vec();
+
+    while !current_terms.is_empty() {
+        let mut merged_terms = HashMap::<Minterm, u64>::new();
+        let mut was_covered = vec![false; current_terms.len()];
+        current_terms.sort_by_key(|term| ones_group_order(&term.term));
+        let ones_groups = ones_groups(current_terms.iter().map(|term| &term.term));
+
+        // As for a single output, terms that can merge are in adjacent groups
+        // and have the same care mask, and must also share an output.
+        for (group, next_group) in ones_groups.iter().zip(ones_groups.iter().skip(1)) {
+            if next_group.n_ones != group.n_ones + 1 {
+                continue;
+            }
+            let next_terms = &current_terms[next_group.range.clone()];
+            for i in group.range.clone() {
+                let term_i = &current_terms[i];
+                let start = next_terms.partition_point(|term| term.term.care < term_i.term.care);
+                for (j, term_j) in next_terms.iter().enumerate().skip(start) {
+                    if term_j.term.care != term_i.term.care {
+                        break;
+                    }
+                    let shared_outputs = term_i.outputs & term_j.outputs;
+                    if shared_outputs == 0 {
+                        continue;
+                    }
+                    if let Some(n) = can_merge(&term_i.term, &term_j.term) {
+                        *merged_terms
+                            .entry(term_i.term.merge(&term_j.term, n))
+                            .or_default() |= shared_outputs;
+                        was_covered[i] |= shared_outputs == term_i.outputs;
+                        was_covered[next_group.range.start + j] |= shared_outputs == term_j.outputs;
+                    }
+                }
+            }
+        }
+        for (i, covered) in was_covered.iter().enumerate() {
+            if !covered {
+                prime_implicants.push(current_terms[i].clone());
+            }
+        }
+
+        if DEV_DEBUG {
+            println!("After merge operation: {merged_terms:?}");
+        }
+        current_terms = merged_terms
+            .into_iter()
+            .map(|(term, outputs)| TaggedMinterm { term, outputs })
+            .collect();
+    }
+
+    prime_implicants
+}
+
+// -----------------------------------------
+// Multi-output chart and minimal cover.
+
+/// Create the combined prime implicant chart, with a column for
+/// each minterm of each function in `functions`, in order.
+pub fn create_multi_output_chart(
+    prime_impls: &[TaggedMinterm],
+    functions: &[Vec<Minterm>],
+) -> PrimeImplicateChart {
+    let mut rows = vec![];
+    for prime_impl in prime_impls {
+        let mut row = vec![];
+        for (output, minterms) in functions.iter().enumerate() {
+            let has_output = prime_impl.has_output(output);
+            row.extend(
+                minterms
+                    .iter()
+                    .map(|minterm| has_output && check_match(&prime_impl.term, minterm)),
+            );
+        }
+        rows.push(row);
+    }
+    PrimeImplicateChart { rows }
+}
+
+/// A cover of several functions by shared product terms.
+#[derive(Debug)]
+pub struct MultiOutputCover {
+    /// The distinct product terms used by any output.
+    pub products: Vec<TaggedMinterm>,
+    /// The product terms in the sum-of-products for each output.
+    pub outputs: Vec<Vec<Minterm>>,
+}
+
+/// Get a cover of `functions` using a minimal number of distinct products,
+/// chosen by `solver`.
+pub fn get_minimal_multi_output_cover(
+    functions: &[Vec<Minterm>],
+    solver: &dyn CoverSolver,
+) -> Result<MultiOutputCover, QmError> {
+    if functions.len() > MAX_OUTPUTS {
+        return Err(QmError::InvalidInput(ParseError::new(
+            0..0,
+            format!("more than {MAX_OUTPUTS} outputs"),
+        )));
+    }
+    check_num_vars(&functions.concat())?;
+    if functions.iter().all(|minterms| minterms.is_empty()) {
+        return Ok(MultiOutputCover {
+            products: vec![],
+            outputs: vec![vec![]; functions.len()],
+        });
+    }
+
+    let tagged_minterms = tag_minterms(functions);
+    let prime_impls = get_multi_output_prime_implicants(&tagged_minterms);
+    let chart = create_multi_output_chart(&prime_impls, functions);
+
+    // The solvers work with plain minterms, so look up tags afterward.
+    let tag_index: HashMap<Minterm, u64> =
+        prime_impls.iter().map(|p| (p.term, p.outputs)).collect();
+    let terms = prime_impls.into_iter().map(|p| p.term).collect::<Vec<_>>();
+
+    let chosen_terms = solver
+        .solve(chart, terms, &ProductCount, &Monitor::NONE)?
+        .terms;
+    let mut products = chosen_terms
+        .into_iter()
+        .map(|term| {
+            let outputs = tag_index[&term];
+            TaggedMinterm { term, outputs }
+        })
+        .collect::<Vec<_>>();
+
+    let outputs = (0..functions.len())
+        .map(|output| irredundant_output_terms(&products, output, &functions[output]))
+        .collect::<Vec<_>>();
+    products.retain(|p| outputs.iter().any(|terms| terms.contains(&p.term)));
+    Ok(MultiOutputCover { products, outputs })
+}
+
+/// Select the products needed for `output` from a cover of all outputs,
+/// dropping any whose minterms are covered by the other products.
+fn irredundant_output_terms(
+    products: &[TaggedMinterm],
+    output: usize,
+    minterms: &[Minterm],
+) -> Vec<Minterm> {
+    let mut terms = products
+        .iter()
+        .filter(|p| p.has_output(output))
+        .map(|p| p.term)
+        .collect::<Vec<_>>();
+
+    let mut i = 0;
+    while i < terms.len() {
+        let is_redundant = minterms
+            .iter()
+            .filter(|minterm| check_match(&terms[i], minterm))
+            .all(|minterm| {
+                terms
+                    .iter()
+                    .enumerate()
+                    .any(|(j, term)| j != i && check_match(term, minterm))
+            });
+        if is_redundant {
+            terms.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    terms
+}
@@ -10,68 +10,225 @@ use std::{
     time::{Duration, Instant},
 };
 
-use super::{Minterm, PrimeImplicateChart};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use super::{
+    Minterm, PrimeImplicateChart,
+    cost::{CostModel, ProductCount, cover_cost, cover_key},
+    error::QmError,
+    progress::{Monitor, Progress},
+};
+
+/// Limits on the work done expanding the product of sums, which can grow
+/// exponentially in the size of the chart. Exceeding a limit aborts with
+/// `QmError::BudgetExceeded`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PetrickBudget {
+    /// Time allowed for expanding the product of sums.
+    pub timeout: Option<Duration>,
+    /// Maximum number of products held at once during the expansion.
+    pub max_products: Option<usize>,
+}
+
+impl PetrickBudget {
+    /// No limits, so the expansion always runs to completion.
+    pub const UNLIMITED: PetrickBudget = PetrickBudget {
+        timeout: None,
+        max_products: None,
+    };
+}
+
+// The budget for one expansion, with the timeout as a deadline,
+// and the monitor for cancelling it.
+struct Limits<'a> {
+    deadline: Option<Instant>,
+    max_products: Option<usize>,
+    monitor: Monitor<'a>,
+}
+
+impl<'a> Limits<'a> {
+    fn new(budget: &PetrickBudget, monitor: &Monitor<'a>) -> Self {
+        Limits {
+            // A timeout too long to represent is no limit at all.
+            deadline: budget
+                .timeout
+                .and_then(|timeout| Instant::now().checked_add(timeout)),
+            max_products: budget.max_products,
+            monitor: *monitor,
+        }
+    }
+
+    // Check for cancellation, the deadline, and that `num_products`
+    // products are allowed.
+    fn check(&self, num_products: usize) -> Result<(), QmError> {
+        self.monitor.check_cancelled()?;
+        if self.max_products.is_some_and(|max| num_products > max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(QmError::BudgetExceeded);
+        }
+        Ok(())
+    }
+}
 
 // --------------------------------------------
 // Bit vector type for use in Petrick's method.
 
+/// Storage for the bits of a `BitVec`. A single `u64` is used when there
+/// are at most 64 prime implicants, which is the common case and the
+/// fastest, or else a vector of words for any number of them.
+trait Words: Clone + Eq + Send + Sync {
+    /// Storage for `len` bits, all zero.
+    fn zeros(len: usize) -> Self;
+    fn words(&self) -> &[u64];
+    fn words_mut(&mut self) -> &mut [u64];
+}
+
+impl Words for u64 {
+    fn zeros(len: usize) -> Self {
+        assert!(len <= 64);
+        0
+    }
+
+    fn words(&self) -> &[u64] {
+        std::slice::from_ref(self)
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        std::slice::from_mut(self)
+    }
+}
+
+impl Words for Vec<u64> {
+    fn zeros(len: usize) -> Self {
+        vec![0; len.div_ceil(64)]
+    }
+
+    fn words(&self) -> &[u64] {
+        self
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        self
+    }
+}
+
 /// Bit vector representing a set of essential prime implicants.
 /// For use in applying Petrick's method to a prime implicant chart.
-#[derive(Clone, Copy, Default)]
-struct BitVec {
-    bits: u64,
+#[derive(Clone, PartialEq, Eq)]
+struct BitVec<W: Words> {
+    words: W,
 }
 
-impl std::fmt::Debug for BitVec {
+impl<W: Words> std::fmt::Debug for BitVec<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({:#b})", self.bits)
+        write!(f, "(")?;
+        for (i, word) in self.words.words().iter().enumerate().rev() {
+            let sep = if i == 0 { "" } else { "_" };
+            write!(f, "{word:#b}{sep}")?;
+        }
+        write!(f, ")")
     }
 }
 
-impl PartialEq for BitVec {
-    fn eq(&self, other: &Self) -> bool {
-        self.bits == other.bits
+/// Order by number of ones, and then by value as a binary number.
+impl<W: Words> Ord for BitVec<W> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.count_ones().cmp(&other.count_ones()).then_with(|| {
+            let words = self.words.words().iter().rev();
+            words.cmp(other.words.words().iter().rev())
+        })
     }
 }
 
-impl BitVec {
+impl<W: Words> PartialOrd for BitVec<W> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Indices of the ones of `word`, offset by `offset`.
+fn word_ones(mut word: u64, offset: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let i = word.trailing_zeros() as usize;
+        word &= word.wrapping_sub(1);
+        (i < 64).then_some(offset + i)
+    })
+}
+
+impl<W: Words> BitVec<W> {
+    /// A bit vector of `len` bits, all zero.
+    pub fn new(len: usize) -> Self {
+        BitVec {
+            words: W::zeros(len),
+        }
+    }
+
     pub fn set_bit(&mut self, i: usize) {
-        assert!(i < 64);
-        self.bits |= 1u64 << i;
+        self.words.words_mut()[i / 64] |= 1u64 << (i % 64);
     }
 
     #[allow(unused)]
     pub fn reset_bit(&mut self, i: usize) {
-        assert!(i < 64);
-        self.bits &= !(1u64 << i);
+        self.words.words_mut()[i / 64] &= !(1u64 << (i % 64));
     }
 
     pub fn get_bit(&self, i: usize) -> bool {
-        assert!(i < 64);
-        (self.bits & (1u64 << i)) != 0
+        (self.words.words()[i / 64] & (1u64 << (i % 64))) != 0
     }
 
     pub fn count_ones(&self) -> u32 {
-        self.bits.count_ones()
+        self.words
+            .words()
+            .iter()
+            .map(|word| word.count_ones())
+            .sum()
     }
 
-    pub fn merge(&mut self, other: &BitVec) {
-        self.bits |= other.bits;
+    /// Number of ones at indices below `i`.
+    pub fn count_ones_below(&self, i: usize) -> u32 {
+        let words = self.words.words();
+        let full_words = words[..i / 64]
+            .iter()
+            .map(|word| word.count_ones())
+            .sum::<u32>();
+        full_words + (words[i / 64] & ((1u64 << (i % 64)) - 1)).count_ones()
     }
 
-    pub fn nonzero_indices(&self) -> Vec<usize> {
-        let mut nonzero_indices = vec![];
-        for i in 0..64 {
-            if self.get_bit(i) {
-                nonzero_indices.push(i);
-            }
+    pub fn merge(&mut self, other: &Self) {
+        for (word, other_word) in self.words.words_mut().iter_mut().zip(other.words.words()) {
+            *word |= other_word;
         }
-        nonzero_indices
+    }
+
+    pub fn nonzero_indices(&self) -> impl Iterator<Item = usize> {
+        self.words
+            .words()
+            .iter()
+            .enumerate()
+            .flat_map(|(i, word)| word_ones(*word, 64 * i))
+    }
+
+    /// Indices of the ones in both this and `other`.
+    pub fn common_indices<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = usize> + 'a {
+        self.words
+            .words()
+            .iter()
+            .zip(other.words.words())
+            .enumerate()
+            .flat_map(|(i, (word, other_word))| word_ones(word & other_word, 64 * i))
     }
 
     #[allow(unused)]
-    pub fn is_subset(&self, other: &BitVec) -> bool {
-        self.bits & other.bits == self.bits
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.words
+            .words()
+            .iter()
+            .zip(other.words.words())
+            .all(|(word, other_word)| word & other_word == *word)
     }
 }
 
@@ -87,7 +244,7 @@ impl std::fmt::Debug for OnesGroup {
     }
 }
 
-impl BitVec {
+impl<W: Words> BitVec<W> {
     pub fn bitvecs_from_chart_col(
         prime_impl_chart: &PrimeImplicateChart,
         col: usize,
@@ -103,7 +260,7 @@ impl BitVec {
         let mut bit_vecs = vec![];
         for (i, row) in rows.iter().enumerate() {
             if row[col] {
-                let mut bit_vec = BitVec::default();
+                let mut bit_vec = BitVec::new(rows.len());
                 bit_vec.set_bit(i);
                 bit_vecs.push(bit_vec);
             }
@@ -112,18 +269,18 @@ impl BitVec {
         bit_vecs
     }
 
-    pub fn bitsort(bitvecs: &mut [BitVec]) -> Vec<OnesGroup> {
-        bitvecs.sort_by(|a, b| {
-            if (a.count_ones()) < (b.count_ones()) {
-                return Ordering::Less;
-            }
-            if (a.count_ones()) > (b.count_ones()) {
-                return Ordering::Greater;
-            }
-            // Same # of 1-bits.
-            a.bits.cmp(&b.bits)
-        });
+    pub fn bitsort(bitvecs: &mut [Self]) -> Vec<OnesGroup> {
+        #[cfg(feature = "parallel")]
+        if use_threads(bitvecs.len()) {
+            // Equal bitvecs are identical, so this is the same order as below.
+            bitvecs.par_sort_unstable();
+            return Self::ones_groups(bitvecs);
+        }
+        bitvecs.sort();
+        Self::ones_groups(bitvecs)
+    }
 
+    fn ones_groups(bitvecs: &[Self]) -> Vec<OnesGroup> {
         // (# ones, starting position of bitvecs with this # ones)
         let mut ones_group_start: Vec<OnesGroup> = vec![];
         for (i, bv) in bitvecs.iter().enumerate() {
@@ -144,53 +301,191 @@ impl BitVec {
 
 /// Get a minimal set of prime implicants for an equivalent expression.
 pub fn get_minimal_sop_terms(
-    mut prime_impl_chart: PrimeImplicateChart,
-    mut prime_impls: Vec<Minterm>,
-) -> (Vec<Minterm>, PetrickTimeInfo) {
-    if prime_impl_chart.rows.is_empty() || prime_impl_chart.rows.first().unwrap().is_empty() {
-        // Ok to panic here because this condition indicates programmer error.
-        panic!("Prime implicant chart has either no rows or no columns.");
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+) -> Result<(Vec<Minterm>, PetrickTimeInfo), QmError> {
+    get_minimal_sop_terms_with_cost(prime_impl_chart, prime_impls, &ProductCount)
+}
+
+/// Get a set of prime implicants for an equivalent expression
+/// that is of minimal cost according to `cost`.
+pub fn get_minimal_sop_terms_with_cost(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+) -> Result<(Vec<Minterm>, PetrickTimeInfo), QmError> {
+    get_minimal_sop_terms_with_budget(
+        prime_impl_chart,
+        prime_impls,
+        cost,
+        &PetrickBudget::UNLIMITED,
+        &Monitor::NONE,
+    )
+}
+
+/// Like `get_minimal_sop_terms_with_cost`, but giving up with
+/// `QmError::BudgetExceeded` if the expansion exceeds `budget`. Progress
+/// is reported to `monitor`, stopping with `QmError::Cancelled` if it
+/// is cancelled.
+pub fn get_minimal_sop_terms_with_budget(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    budget: &PetrickBudget,
+    monitor: &Monitor,
+) -> Result<(Vec<Minterm>, PetrickTimeInfo), QmError> {
+    // Use single-word bit vectors when there are few enough prime implicants.
+    if prime_impls.len() <= 64 {
+        minimal_sop_terms::<u64>(prime_impl_chart, prime_impls, cost, budget, monitor)
+    } else {
+        minimal_sop_terms::<Vec<u64>>(prime_impl_chart, prime_impls, cost, budget, monitor)
     }
-    // This version currently supports at most 6-variables, but could be extended.
-    assert!(prime_impls.len() == prime_impl_chart.rows.len());
-    assert!(prime_impl_chart.rows.first().unwrap().len() <= 64);
+}
 
+fn minimal_sop_terms<W: Words>(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    budget: &PetrickBudget,
+    monitor: &Monitor,
+) -> Result<(Vec<Minterm>, PetrickTimeInfo), QmError> {
     let mut time = PetrickTimeInfo::default();
+    let limits = Limits::new(budget, monitor);
+    let products =
+        expand_cover_products::<W>(prime_impl_chart, prime_impls, false, &limits, &mut time)?;
+    let mut min_expr_terms = products.ess_prime_impls;
 
-    // Remove essential prime implicants from chart.
-    let (mut min_expr_terms, remaining_cols) =
-        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, Some(&mut time));
-    if remaining_cols.is_empty() {
-        // Indicates all prime impls were essential, so we're done.
-        return (min_expr_terms, time);
+    // Every cover is a superset of one of the remaining products,
+    // so the one of least cost is a minimal cover.
+    let start = Instant::now();
+    let chosen_min_terms = products
+        .bitvecs
+        .iter()
+        .map(|bitvec| terms_for_bitvec(bitvec, &products.prime_impls))
+        .min_by_key(|terms| cover_key(cost, terms))
+        .unwrap();
+    min_expr_terms.extend(chosen_min_terms);
+    time.second_loop += start.elapsed();
+
+    Ok((min_expr_terms, time))
+}
+
+/// Get all covers of minimal cost according to `cost`, each as a set of prime
+/// implicants for an equivalent expression, along with the number of such covers.
+/// At most `max_covers` of them are returned, if given. Covers are irredundant,
+/// i.e. no prime implicant can be left out of one.
+pub fn get_all_minimal_sop_terms_with_cost(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    max_covers: Option<usize>,
+) -> Result<(Vec<Vec<Minterm>>, usize), QmError> {
+    if prime_impls.len() <= 64 {
+        all_minimal_sop_terms::<u64>(prime_impl_chart, prime_impls, cost, max_covers)
+    } else {
+        all_minimal_sop_terms::<Vec<u64>>(prime_impl_chart, prime_impls, cost, max_covers)
     }
+}
+
+fn all_minimal_sop_terms<W: Words>(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    max_covers: Option<usize>,
+) -> Result<(Vec<Vec<Minterm>>, usize), QmError> {
+    let mut time = PetrickTimeInfo::default();
+    let limits = Limits::new(&PetrickBudget::UNLIMITED, &Monitor::NONE);
+    let products =
+        expand_cover_products::<W>(prime_impl_chart, prime_impls, true, &limits, &mut time)?;
+
+    let candidates = products
+        .bitvecs
+        .iter()
+        .map(|bitvec| terms_for_bitvec(bitvec, &products.prime_impls))
+        .collect::<Vec<_>>();
+    let min_cost = candidates
+        .iter()
+        .map(|terms| cover_cost(cost, terms))
+        .min()
+        .unwrap();
+    let min_covers = candidates
+        .into_iter()
+        .filter(|terms| cover_cost(cost, terms) == min_cost)
+        .collect::<Vec<_>>();
+
+    let num_covers = min_covers.len();
+    let covers = min_covers
+        .into_iter()
+        .take(max_covers.unwrap_or(usize::MAX))
+        .map(|terms| [products.ess_prime_impls.clone(), terms].concat())
+        .collect();
+    Ok((covers, num_covers))
+}
 
-    // Simplify remaining terms with boolean logic rules.
-    let mut current_bitvecs: Vec<BitVec> = vec![BitVec::default()];
+/// The expanded product of sums for a prime implicant chart.
+struct CoverProducts<W: Words> {
+    ess_prime_impls: Vec<Minterm>,
+    /// Products of the remaining prime implicants, each a cover of
+    /// the columns not covered by the essential prime implicants.
+    bitvecs: Vec<BitVec<W>>,
+    /// The remaining prime implicants, indexed by the bit vectors.
+    prime_impls: Vec<Minterm>,
+}
+
+// Expand the product of sums for the chart, after removing essential prime
+// implicants. If `irredundant` is set, products that are supersets of others
+// are removed at the end.
+fn expand_cover_products<W: Words>(
+    mut prime_impl_chart: PrimeImplicateChart,
+    mut prime_impls: Vec<Minterm>,
+    irredundant: bool,
+    limits: &Limits,
+    time: &mut PetrickTimeInfo,
+) -> Result<CoverProducts<W>, QmError> {
+    prime_impl_chart.check_shape(&prime_impls)?;
+
+    // Remove essential prime implicants from chart.
+    let (ess_prime_impls, remaining_cols) =
+        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, Some(time))?;
+    limits.monitor.report(Progress::EssentialPrimeImplicants {
+        num_essential: ess_prime_impls.len(),
+        num_remaining_cols: remaining_cols.len(),
+    });
+
+    // Simplify remaining terms with boolean logic rules. If all prime
+    // impls were essential, the only product is the empty one.
+    let mut current_bitvecs: Vec<BitVec<W>> = vec![BitVec::new(prime_impls.len())];
     let col_bitvecs = remaining_cols
         .into_iter()
-        .map(|rem_col_i| BitVec::bitvecs_from_chart_col(&prime_impl_chart, rem_col_i, &mut time))
+        .map(|rem_col_i| BitVec::bitvecs_from_chart_col(&prime_impl_chart, rem_col_i, time))
         .filter(|vecs| !vecs.is_empty())
         .collect::<Vec<_>>();
     let start = Instant::now();
     for (i, next_col_bitvecs) in col_bitvecs.iter().enumerate() {
         time.pairwise_and_calls += 1;
-        current_bitvecs = pairwise_and(&current_bitvecs, next_col_bitvecs, &mut time);
-        if i < col_bitvecs.len() - 1 {
-            remove_redundant(&mut current_bitvecs, &mut time);
+        current_bitvecs = pairwise_and(&current_bitvecs, next_col_bitvecs, limits, time)?;
+        if irredundant || i < col_bitvecs.len() - 1 {
+            remove_redundant(&mut current_bitvecs, limits, time)?;
         }
+        limits.monitor.report(Progress::PetrickColumn {
+            column: i,
+            num_columns: col_bitvecs.len(),
+            num_products: current_bitvecs.len(),
+        });
     }
     time.first_loop += start.elapsed();
 
-    let start = Instant::now();
     let _ = BitVec::bitsort(&mut current_bitvecs);
-    let chosen_min_bitvec = current_bitvecs.first().unwrap();
-    for i in chosen_min_bitvec.nonzero_indices() {
-        min_expr_terms.push(prime_impls.get(i).unwrap().clone());
-    }
-    time.second_loop += start.elapsed();
+    Ok(CoverProducts {
+        ess_prime_impls,
+        bitvecs: current_bitvecs,
+        prime_impls,
+    })
+}
 
-    (min_expr_terms, time)
+// The prime implicants for the nonzero bits of `bitvec`.
+fn terms_for_bitvec<W: Words>(bitvec: &BitVec<W>, prime_impls: &[Minterm]) -> Vec<Minterm> {
+    bitvec.nonzero_indices().map(|i| prime_impls[i]).collect()
 }
 
 /// Computes the logical 'and' to build up a set of prime implicants
@@ -199,26 +494,45 @@ pub fn get_minimal_sop_terms(
 /// **Note:** The actual bitwise operation performed on bit vectors is
 /// the logical 'or', because a bit vector is interpreted as the 'and'
 /// of the terms corresponding to its nonzero digits.
-fn pairwise_and(
-    current_bitvecs: &[BitVec],
-    next_col_bitvecs: &[BitVec],
+fn pairwise_and<W: Words>(
+    current_bitvecs: &[BitVec<W>],
+    next_col_bitvecs: &[BitVec<W>],
+    limits: &Limits,
     time: &mut PetrickTimeInfo,
-) -> Vec<BitVec> {
+) -> Result<Vec<BitVec<W>>, QmError> {
     let start = Instant::now();
+    #[cfg(feature = "parallel")]
+    let mut merged_bitvecs = if use_threads(current_bitvecs.len()) {
+        parallel_merge_products(current_bitvecs, next_col_bitvecs, limits)?
+    } else {
+        merge_products(current_bitvecs, next_col_bitvecs, limits)?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let mut merged_bitvecs = merge_products(current_bitvecs, next_col_bitvecs, limits)?;
+
+    // Sort and deduplicate.
+    let _ = BitVec::bitsort(&mut merged_bitvecs);
+    merged_bitvecs.dedup();
+    time.pairwise_and += start.elapsed();
+    Ok(merged_bitvecs)
+}
+
+// Merge each of `current_bitvecs` with each of `next_col_bitvecs`.
+fn merge_products<W: Words>(
+    current_bitvecs: &[BitVec<W>],
+    next_col_bitvecs: &[BitVec<W>],
+    limits: &Limits,
+) -> Result<Vec<BitVec<W>>, QmError> {
     let mut merged_bitvecs = vec![];
     for c_bitvec in current_bitvecs {
+        limits.check(merged_bitvecs.len() + next_col_bitvecs.len())?;
         for n_bitvec in next_col_bitvecs {
-            let mut new_bitvec = *c_bitvec;
+            let mut new_bitvec = c_bitvec.clone();
             new_bitvec.merge(n_bitvec);
             merged_bitvecs.push(new_bitvec);
         }
     }
-
-    // Sort and deduplicate.
-    let _ = BitVec::bitsort(&mut merged_bitvecs);
-    merged_bitvecs.dedup();
-    time.pairwise_and += start.elapsed();
-    merged_bitvecs
+    Ok(merged_bitvecs)
 }
 
 const DEV_DEBUG: bool = false;
@@ -227,13 +541,16 @@ const DEV_DEBUG: bool = false;
 /// As a side effect, sorts reduced `bitvecs`.
 ///
 /// Precondition: `bitvecs` has been sorted and deduplicated.
-fn remove_redundant(bitvecs: &mut Vec<BitVec>, time: &mut PetrickTimeInfo) {
+fn remove_redundant<W: Words>(
+    bitvecs: &mut Vec<BitVec<W>>,
+    limits: &Limits,
+    time: &mut PetrickTimeInfo,
+) -> Result<(), QmError> {
     if bitvecs.is_empty() {
-        return;
+        return Ok(());
     }
     let start = Instant::now();
     let ones_group_start = BitVec::bitsort(bitvecs);
-    let mut to_remove = vec![false; bitvecs.len()];
 
     if DEV_DEBUG {
         println!("{ones_group_start:?} - {}", bitvecs.len());
@@ -241,31 +558,187 @@ fn remove_redundant(bitvecs: &mut Vec<BitVec>, time: &mut PetrickTimeInfo) {
 
     // Find redundant bitvecs.
     let start_inner = Instant::now();
-    for i in 0..ones_group_start.last().unwrap().start_offset {
-        // If we removed bitvec i, then we'd have removed its supersets also.
-        if to_remove[i] {
-            continue;
-        }
-        let bitvec_i = &bitvecs[i];
-        let ogs_n = ones_group_start
+    let to_remove = find_redundant(bitvecs, &ones_group_start, limits)?;
+    time.remove_redundant_first_loop += start_inner.elapsed();
+
+    // Remove redundant bitvecs.
+    let num_bitvecs = bitvecs.len();
+    let mut to_remove = to_remove.into_iter();
+    bitvecs.retain(|_| !to_remove.next().unwrap());
+    time.redundant_removed.push(num_bitvecs - bitvecs.len());
+    time.remove_redundant += start.elapsed();
+    Ok(())
+}
+
+// Mark the bitvecs that are supersets of others, given the sorted `bitvecs`
+// and their groups by number of ones. Bitvecs with the same number of ones
+// can't be subsets of each other, so each group is checked against a trie
+// of the bitvecs kept from the groups before it.
+fn find_redundant<W: Words>(
+    bitvecs: &[BitVec<W>],
+    ones_group_start: &[OnesGroup],
+    limits: &Limits,
+) -> Result<Vec<bool>, QmError> {
+    let num_bits = bitvecs
+        .first()
+        .map_or(0, |bitvec| 64 * bitvec.words.words().len());
+    let mut kept = SubsetTrie::new(num_bits);
+    let mut to_remove = Vec::with_capacity(bitvecs.len());
+    let group_ends = ones_group_start
+        .iter()
+        .skip(1)
+        .map(|group| group.start_offset)
+        .chain([bitvecs.len()]);
+    for (group, end) in ones_group_start.iter().zip(group_ends) {
+        limits.check(bitvecs.len())?;
+        let group_bitvecs = &bitvecs[group.start_offset..end];
+
+        #[cfg(feature = "parallel")]
+        let group_to_remove = if use_threads(group_bitvecs.len()) {
+            group_bitvecs
+                .par_iter()
+                .map(|bitvec| kept.contains_subset_of(bitvec))
+                .collect::<Vec<_>>()
+        } else {
+            group_bitvecs
+                .iter()
+                .map(|bitvec| kept.contains_subset_of(bitvec))
+                .collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let group_to_remove = group_bitvecs
             .iter()
-            .position(|OnesGroup { n_ones, .. }| *n_ones == bitvec_i.count_ones())
-            .unwrap();
-        for j in ones_group_start[ogs_n + 1].start_offset..bitvecs.len() {
-            if !to_remove[j] && (bitvecs[i].bits & bitvecs[j].bits == bitvecs[i].bits) {
-                to_remove[j] = true;
+            .map(|bitvec| kept.contains_subset_of(bitvec))
+            .collect::<Vec<_>>();
+
+        for (bitvec, remove) in group_bitvecs.iter().zip(&group_to_remove) {
+            if !remove {
+                kept.insert(bitvec);
             }
         }
+        to_remove.extend(group_to_remove);
     }
-    time.remove_redundant_first_loop += start_inner.elapsed();
+    Ok(to_remove)
+}
 
-    // Remove redundant bitvecs.
-    for i in (0..bitvecs.len()).rev() {
-        if to_remove[i] {
-            bitvecs.remove(i);
+/// A set of bitvecs that can be queried for a subset of a given bitvec.
+/// Each bitvec is a path from the root through its nonzero indices in
+/// increasing order, so a query only follows the children for indices
+/// in the given bitvec.
+struct SubsetTrie<W: Words> {
+    /// Number of bits in each node's `child_bits`.
+    num_bits: usize,
+    nodes: Vec<TrieNode<W>>,
+}
+
+struct TrieNode<W: Words> {
+    /// The indices with a child node.
+    child_bits: BitVec<W>,
+    /// The child nodes, in order of their indices.
+    children: Vec<usize>,
+    /// Whether a bitvec ends at this node.
+    is_end: bool,
+}
+
+impl<W: Words> TrieNode<W> {
+    fn new(num_bits: usize) -> Self {
+        TrieNode {
+            child_bits: BitVec::new(num_bits),
+            children: vec![],
+            is_end: false,
         }
     }
-    time.remove_redundant += start.elapsed();
+
+    // Position in `children` of the child for index `bit`.
+    fn child_position(&self, bit: usize) -> usize {
+        self.child_bits.count_ones_below(bit) as usize
+    }
+}
+
+impl<W: Words> SubsetTrie<W> {
+    fn new(num_bits: usize) -> Self {
+        SubsetTrie {
+            num_bits,
+            nodes: vec![TrieNode::new(num_bits)],
+        }
+    }
+
+    fn insert(&mut self, bitvec: &BitVec<W>) {
+        let mut node = 0;
+        for bit in bitvec.nonzero_indices() {
+            let position = self.nodes[node].child_position(bit);
+            if self.nodes[node].child_bits.get_bit(bit) {
+                node = self.nodes[node].children[position];
+            } else {
+                self.nodes.push(TrieNode::new(self.num_bits));
+                let child = self.nodes.len() - 1;
+                self.nodes[node].child_bits.set_bit(bit);
+                self.nodes[node].children.insert(position, child);
+                node = child;
+            }
+        }
+        self.nodes[node].is_end = true;
+    }
+
+    fn contains_subset_of(&self, bitvec: &BitVec<W>) -> bool {
+        self.contains_subset_below(0, bitvec)
+    }
+
+    fn contains_subset_below(&self, node: usize, bitvec: &BitVec<W>) -> bool {
+        let node = &self.nodes[node];
+        node.is_end
+            || node.child_bits.common_indices(bitvec).any(|bit| {
+                self.contains_subset_below(node.children[node.child_position(bit)], bitvec)
+            })
+    }
+}
+
+// ---------------------------------------
+// Parallel versions of the steps above.
+
+/// Least number of bitvecs for which work is split across threads,
+/// since for fewer the overhead outweighs the gain.
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_BITVECS: usize = 4096;
+
+/// Number of bitvecs merged by each parallel task in `pairwise_and`.
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 1024;
+
+// Whether to split the work on `len` bitvecs across threads. Within a
+// single-thread pool the serial versions are used.
+#[cfg(feature = "parallel")]
+fn use_threads(len: usize) -> bool {
+    len >= PARALLEL_MIN_BITVECS && rayon::current_num_threads() > 1
+}
+
+// Like `merge_products`, merging chunks of `current_bitvecs` in parallel.
+// After sorting and deduplicating, the result is the same.
+#[cfg(feature = "parallel")]
+fn parallel_merge_products<W: Words>(
+    current_bitvecs: &[BitVec<W>],
+    next_col_bitvecs: &[BitVec<W>],
+    limits: &Limits,
+) -> Result<Vec<BitVec<W>>, QmError> {
+    // The serial version fails if the total exceeds the maximum
+    // number of products, so check that first.
+    limits.check(current_bitvecs.len() * next_col_bitvecs.len())?;
+    let chunks = current_bitvecs
+        .par_chunks(PARALLEL_CHUNK_SIZE)
+        .map(|chunk| {
+            limits.check(0)?;
+            let mut merged_bitvecs = Vec::with_capacity(chunk.len() * next_col_bitvecs.len());
+            for c_bitvec in chunk {
+                for n_bitvec in next_col_bitvecs {
+                    let mut new_bitvec = c_bitvec.clone();
+                    new_bitvec.merge(n_bitvec);
+                    merged_bitvecs.push(new_bitvec);
+                }
+            }
+            Ok(merged_bitvecs)
+        })
+        .collect::<Result<Vec<_>, QmError>>()?;
+    Ok(chunks.concat())
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -286,8 +759,8 @@ pub fn remove_essential_prime_impls(
     prime_impl_chart: &mut PrimeImplicateChart,
     prime_impls: &mut Vec<Minterm>,
     time: Option<&mut PetrickTimeInfo>,
-) -> (Vec<Minterm>, Vec<usize>) {
-    assert!(prime_impls.len() == prime_impl_chart.rows.len());
+) -> Result<(Vec<Minterm>, Vec<usize>), QmError> {
+    prime_impl_chart.check_shape(prime_impls)?;
     let start = Instant::now();
     let num_cols = prime_impl_chart.rows.first().unwrap().len();
 
@@ -314,14 +787,14 @@ pub fn remove_essential_prime_impls(
     let mut covered_by_prime = vec![false; num_cols];
 
     // Mark essential prime implicants and columns they cover.
-    for val in remove_cols.iter_mut() {
+    for (col_i, val) in remove_cols.iter().enumerate() {
         if let RowCount::One(row_i) = val {
             is_essential[*row_i] = true;
             for (j, covered) in prime_impl_chart.rows[*row_i].iter().enumerate() {
                 covered_by_prime[j] |= covered;
             }
         } else if matches!(val, RowCount::None) {
-            panic!("An implicant chart column that was not covered by any row.");
+            return Err(QmError::UncoveredColumn(col_i));
         }
     }
     // Keep columns that aren't covered by a prime implicant.
@@ -359,19 +832,21 @@ pub fn remove_essential_prime_impls(
     if let Some(time) = time {
         time.remove_essential_prime_impls += start.elapsed();
     }
-    (ess_prime_impls, remaining_cols)
+    Ok((ess_prime_impls, remaining_cols))
 }
 
 // -----------------------------
 // Timing data for optimization.
 
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct PetrickTimeInfo {
     pub remove_essential_prime_impls: Duration,
     pub bitvecs_from_chart_cols: Duration,
 
     pub remove_redundant: Duration,
     pub remove_redundant_first_loop: Duration,
+    /// Number of bitvecs removed as redundant by each pass.
+    pub redundant_removed: Vec<usize>,
 
     pub first_loop: Duration,
     pub second_loop: Duration,
@@ -414,6 +889,14 @@ impl PetrickTimeInfo {
         )
         .unwrap();
 
+        writeln!(
+            message,
+            "-- redundant bitvecs removed:    {:>5}  (by pass: {:?})",
+            self.redundant_removed.iter().sum::<usize>(),
+            self.redundant_removed
+        )
+        .unwrap();
+
         writeln!(message).unwrap();
         writeln!(
             message,
@@ -0,0 +1,475 @@
+//! Petrick's method on a zero-suppressed decision diagram (ZDD). Rather
+//! than a list of bit vectors, the products of the expanded product of sums
+//! are held as a family of sets of prime implicants in a ZDD, which shares
+//! the common parts of the products. Multiplying in a column and removing
+//! products that are supersets of others are both operations on the ZDD,
+//! and the product of least cost is found by a pass over its nodes.
+//!
+//! Gives the same cover as the `petrick` module, using much less memory
+//! when there are many products.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Minterm, PrimeImplicateChart,
+    cost::{CostModel, ProductCount},
+    error::QmError,
+    petrick::remove_essential_prime_impls,
+    progress::{Monitor, Progress},
+};
+
+/// Number of nodes in the table before unreachable nodes are collected.
+const MIN_NODES_TO_COLLECT: usize = 1 << 16;
+
+/// Get a minimal set of prime implicants for an equivalent expression.
+pub fn get_minimal_sop_terms(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+) -> Result<(Vec<Minterm>, ZddStats), QmError> {
+    get_minimal_sop_terms_with_cost(prime_impl_chart, prime_impls, &ProductCount)
+}
+
+/// Get a set of prime implicants for an equivalent expression
+/// that is of minimal cost according to `cost`.
+pub fn get_minimal_sop_terms_with_cost(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+) -> Result<(Vec<Minterm>, ZddStats), QmError> {
+    get_minimal_sop_terms_with_monitor(prime_impl_chart, prime_impls, cost, &Monitor::NONE)
+}
+
+/// Like `get_minimal_sop_terms_with_cost`, reporting each column multiplied
+/// in to `monitor`, and stopping if it is cancelled.
+pub fn get_minimal_sop_terms_with_monitor(
+    mut prime_impl_chart: PrimeImplicateChart,
+    mut prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    monitor: &Monitor,
+) -> Result<(Vec<Minterm>, ZddStats), QmError> {
+    prime_impl_chart.check_shape(&prime_impls)?;
+    let mut stats = ZddStats::default();
+
+    // Remove essential prime implicants from chart.
+    let (mut min_expr_terms, remaining_cols) =
+        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, None)?;
+    monitor.report(Progress::EssentialPrimeImplicants {
+        num_essential: min_expr_terms.len(),
+        num_remaining_cols: remaining_cols.len(),
+    });
+
+    // Multiply in the sum of the rows covering each column, keeping only
+    // the products that aren't supersets of others.
+    let start = Instant::now();
+    let mut zdd = Zdd::default();
+    let mut products = ONE;
+    for (i, col) in remaining_cols.iter().enumerate() {
+        monitor.check_cancelled()?;
+        let in_col = prime_impl_chart
+            .rows
+            .iter()
+            .map(|row| row[*col])
+            .collect::<Vec<_>>();
+        products = zdd.multiply_sum(products, &in_col);
+        zdd.clear_caches();
+
+        stats.max_nodes = stats.max_nodes.max(zdd.nodes.len());
+        if zdd.nodes.len() >= MIN_NODES_TO_COLLECT.max(2 * stats.num_nodes) {
+            products = zdd.collect(products);
+        }
+        stats.num_nodes = zdd.reachable_nodes(products);
+        monitor.report(Progress::PetrickColumn {
+            column: i,
+            num_columns: remaining_cols.len(),
+            num_products: zdd.count(products),
+        });
+    }
+    stats.num_products = zdd.count(products);
+    stats.expand += start.elapsed();
+
+    // Every cover is a superset of one of the products, so the one
+    // of least cost is a minimal cover.
+    let start = Instant::now();
+    let row_keys = prime_impls
+        .iter()
+        .map(|term| (cost.term_cost(term), term.num_literals()))
+        .collect::<Vec<_>>();
+    let Some(rows) = zdd.min_cost_set(products, &row_keys) else {
+        return Err(QmError::UncoveredColumn(remaining_cols[0]));
+    };
+    min_expr_terms.extend(rows.into_iter().map(|row| prime_impls[row]));
+    stats.min_cost += start.elapsed();
+
+    Ok((min_expr_terms, stats))
+}
+
+/// Statistics about a run of Petrick's method on a ZDD.
+#[derive(Debug, Default)]
+pub struct ZddStats {
+    /// Largest number of nodes in the node table, including unreachable ones.
+    pub max_nodes: usize,
+    /// Number of nodes in the diagram of the final products.
+    pub num_nodes: usize,
+    /// Number of products after the last column, none a superset of another.
+    pub num_products: usize,
+    /// Time spent multiplying in the columns.
+    pub expand: Duration,
+    /// Time spent finding the product of least cost.
+    pub min_cost: Duration,
+}
+
+impl ZddStats {
+    pub fn get_report(&self) -> String {
+        format!(
+            "ZDD run time:\n\
+             -- expand:                       {:>5} ms\n\
+             -- min_cost:                     {:>5} ms\n\
+             \n\
+             -- max_nodes:                    {:>5}\n\
+             -- num_nodes:                    {:>5}\n\
+             -- num_products:                 {:>5}\n",
+            self.expand.as_millis(),
+            self.min_cost.as_millis(),
+            self.max_nodes,
+            self.num_nodes,
+            self.num_products,
+        )
+    }
+}
+
+// -----------------------------------------
+// Zero-suppressed decision diagram of sets.
+
+/// Index of a node in the node table, for a family of sets of rows.
+type NodeId = u32;
+
+/// The empty family.
+const ZERO: NodeId = 0;
+/// The family holding only the empty set.
+const ONE: NodeId = 1;
+
+/// A family of sets: those in `lo`, which don't contain `var`, along with
+/// `var` added to each set in `hi`. Variables decrease from the root, so
+/// both `lo` and `hi` only hold sets of variables below `var`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    var: u32,
+    lo: NodeId,
+    hi: NodeId,
+}
+
+/// A table of nodes, each a unique family of sets, along with caches of
+/// the operations on them.
+struct Zdd {
+    nodes: Vec<Node>,
+    unique: HashMap<Node, NodeId>,
+    union_cache: HashMap<(NodeId, NodeId), NodeId>,
+    join_cache: HashMap<(NodeId, NodeId), NodeId>,
+    nonsup_cache: HashMap<(NodeId, NodeId), NodeId>,
+    split_cache: HashMap<NodeId, (NodeId, NodeId)>,
+}
+
+impl Default for Zdd {
+    fn default() -> Self {
+        // Placeholders for the terminals, which have no variable.
+        let terminal = Node {
+            var: u32::MAX,
+            lo: ZERO,
+            hi: ZERO,
+        };
+        Zdd {
+            nodes: vec![terminal, terminal],
+            unique: HashMap::new(),
+            union_cache: HashMap::new(),
+            join_cache: HashMap::new(),
+            nonsup_cache: HashMap::new(),
+            split_cache: HashMap::new(),
+        }
+    }
+}
+
+impl Zdd {
+    /// The node for `var`, `lo` and `hi`, where a node whose `hi` is empty
+    /// is just `lo`.
+    fn node(&mut self, var: u32, lo: NodeId, hi: NodeId) -> NodeId {
+        if hi == ZERO {
+            return lo;
+        }
+        let node = Node { var, lo, hi };
+        if let Some(id) = self.unique.get(&node) {
+            return *id;
+        }
+        let id = NodeId::try_from(self.nodes.len()).expect("too many ZDD nodes");
+        self.nodes.push(node);
+        self.unique.insert(node, id);
+        id
+    }
+
+    // Variable of the node, with the terminals below every variable.
+    fn var(&self, id: NodeId) -> Option<u32> {
+        (id > ONE).then(|| self.nodes[id as usize].var)
+    }
+
+    // The sets of `id` without and with `var`, the latter with `var` removed.
+    fn cofactors(&self, id: NodeId, var: u32) -> (NodeId, NodeId) {
+        match self.var(id) {
+            Some(v) if v == var => {
+                let node = self.nodes[id as usize];
+                (node.lo, node.hi)
+            }
+            _ => (id, ZERO),
+        }
+    }
+
+    /// The family of singleton sets, one for each of `vars`.
+    fn singletons(&mut self, vars: impl Iterator<Item = usize>) -> NodeId {
+        let mut vars = vars.collect::<Vec<_>>();
+        vars.sort_unstable();
+        vars.into_iter()
+            .fold(ZERO, |family, var| self.node(var as u32, family, ONE))
+    }
+
+    /// The sets in either `a` or `b`.
+    fn union(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        if a == ZERO || a == b {
+            return b;
+        }
+        if b == ZERO {
+            return a;
+        }
+        let key = (a.min(b), a.max(b));
+        if let Some(id) = self.union_cache.get(&key) {
+            return *id;
+        }
+        let var = self.var(a).max(self.var(b)).unwrap();
+        let (a_lo, a_hi) = self.cofactors(a, var);
+        let (b_lo, b_hi) = self.cofactors(b, var);
+        let lo = self.union(a_lo, b_lo);
+        let hi = self.union(a_hi, b_hi);
+        let id = self.node(var, lo, hi);
+        self.union_cache.insert(key, id);
+        id
+    }
+
+    /// The unions of a set in `a` with a set in `b`, i.e. the products
+    /// when multiplying out `a` and `b` as sums of products.
+    fn join(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        if a == ZERO || b == ZERO {
+            return ZERO;
+        }
+        if a == ONE {
+            return b;
+        }
+        if b == ONE {
+            return a;
+        }
+        let key = (a.min(b), a.max(b));
+        if let Some(id) = self.join_cache.get(&key) {
+            return *id;
+        }
+        let var = self.var(a).max(self.var(b)).unwrap();
+        let (a_lo, a_hi) = self.cofactors(a, var);
+        let (b_lo, b_hi) = self.cofactors(b, var);
+        let lo = self.join(a_lo, b_lo);
+        // Sets with `var` come from a set with `var` in either `a` or `b`.
+        let hi_hi = self.join(a_hi, b_hi);
+        let hi_lo = self.join(a_hi, b_lo);
+        let lo_hi = self.join(a_lo, b_hi);
+        let hi = self.union(hi_hi, hi_lo);
+        let hi = self.union(hi, lo_hi);
+        let id = self.node(var, lo, hi);
+        self.join_cache.insert(key, id);
+        id
+    }
+
+    /// The minimal sets of the product of `products` and the sum of the
+    /// variables `var` with `in_sum[var]` set, where no set of `products`
+    /// is a superset of another.
+    fn multiply_sum(&mut self, products: NodeId, in_sum: &[bool]) -> NodeId {
+        let vars = (0..in_sum.len()).filter(|var| in_sum[*var]);
+        let sum = self.singletons(vars);
+        // Products with a variable of the sum are unchanged, and the others
+        // gain one of its variables. These are minimal among themselves, so
+        // only supersets of the unchanged products need to be removed.
+        let (missing, hit) = self.split(products, in_sum);
+        let joined = self.join(missing, sum);
+        let joined = self.nonsuperset(joined, hit);
+        self.union(hit, joined)
+    }
+
+    /// The sets of `a` with none of the variables `var` with `vars[var]`
+    /// set, and the sets with at least one of them.
+    fn split(&mut self, a: NodeId, vars: &[bool]) -> (NodeId, NodeId) {
+        if a <= ONE {
+            return (a, ZERO);
+        }
+        if let Some(ids) = self.split_cache.get(&a) {
+            return *ids;
+        }
+        let node = self.nodes[a as usize];
+        let (lo_missing, lo_hit) = self.split(node.lo, vars);
+        let ids = if vars[node.var as usize] {
+            (lo_missing, self.node(node.var, lo_hit, node.hi))
+        } else {
+            let (hi_missing, hi_hit) = self.split(node.hi, vars);
+            (
+                self.node(node.var, lo_missing, hi_missing),
+                self.node(node.var, lo_hit, hi_hit),
+            )
+        };
+        self.split_cache.insert(a, ids);
+        ids
+    }
+
+    /// The sets of `a` that aren't a superset of any set of `b`.
+    fn nonsuperset(&mut self, a: NodeId, b: NodeId) -> NodeId {
+        if a == ZERO || b == ZERO {
+            return a;
+        }
+        if b == ONE || a == b {
+            // Every set is a superset of the empty set, and of itself.
+            return ZERO;
+        }
+        if let Some(id) = self.nonsup_cache.get(&(a, b)) {
+            return *id;
+        }
+        let b_var = self.var(b).unwrap();
+        let id = match self.var(a) {
+            Some(var) if var >= b_var => {
+                let (a_lo, a_hi) = self.cofactors(a, var);
+                let (b_lo, b_hi) = self.cofactors(b, var);
+                let lo = self.nonsuperset(a_lo, b_lo);
+                let hi = self.nonsuperset(a_hi, b_lo);
+                let hi = self.nonsuperset(hi, b_hi);
+                self.node(var, lo, hi)
+            }
+            // No set of `a` has the top variable of `b`, so only the
+            // sets of `b` without it may be subsets.
+            _ => {
+                let b_lo = self.nodes[b as usize].lo;
+                self.nonsuperset(a, b_lo)
+            }
+        };
+        self.nonsup_cache.insert((a, b), id);
+        id
+    }
+
+    fn clear_caches(&mut self) {
+        self.union_cache.clear();
+        self.join_cache.clear();
+        self.nonsup_cache.clear();
+        self.split_cache.clear();
+    }
+
+    /// Rebuild the table with only the nodes reachable from `root`,
+    /// returning the new id of `root`.
+    fn collect(&mut self, root: NodeId) -> NodeId {
+        let mut new_zdd = Zdd::default();
+        let mut new_ids = HashMap::from([(ZERO, ZERO), (ONE, ONE)]);
+        let new_root = self.copy_to(root, &mut new_zdd, &mut new_ids);
+        *self = new_zdd;
+        new_root
+    }
+
+    fn copy_to(
+        &self,
+        id: NodeId,
+        new_zdd: &mut Zdd,
+        new_ids: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if let Some(new_id) = new_ids.get(&id) {
+            return *new_id;
+        }
+        let node = self.nodes[id as usize];
+        let lo = self.copy_to(node.lo, new_zdd, new_ids);
+        let hi = self.copy_to(node.hi, new_zdd, new_ids);
+        let new_id = new_zdd.node(node.var, lo, hi);
+        new_ids.insert(id, new_id);
+        new_id
+    }
+
+    /// Number of nonterminal nodes reachable from `root`.
+    fn reachable_nodes(&self, root: NodeId) -> usize {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![root];
+        let mut num_nodes = 0;
+        while let Some(id) = stack.pop() {
+            if id <= ONE || seen[id as usize] {
+                continue;
+            }
+            seen[id as usize] = true;
+            num_nodes += 1;
+            let node = self.nodes[id as usize];
+            stack.extend([node.lo, node.hi]);
+        }
+        num_nodes
+    }
+
+    /// Number of sets in `root`, saturating at `usize::MAX`.
+    fn count(&self, root: NodeId) -> usize {
+        let mut counts = HashMap::from([(ZERO, 0), (ONE, 1)]);
+        self.count_below(root, &mut counts)
+    }
+
+    fn count_below(&self, id: NodeId, counts: &mut HashMap<NodeId, usize>) -> usize {
+        if let Some(count) = counts.get(&id) {
+            return *count;
+        }
+        let node = self.nodes[id as usize];
+        let count = self
+            .count_below(node.lo, counts)
+            .saturating_add(self.count_below(node.hi, counts));
+        counts.insert(id, count);
+        count
+    }
+
+    /// The set of `root` with the least total of `keys`, and then fewest
+    /// variables, or `None` if `root` is empty. Of those, the one that is
+    /// least as a binary number is chosen, as in the `petrick` module.
+    fn min_cost_set(&self, root: NodeId, keys: &[(u64, usize)]) -> Option<Vec<usize>> {
+        let mut best = HashMap::from([(ZERO, None), (ONE, Some((0, 0, 0)))]);
+        self.min_key_below(root, keys, &mut best)?;
+
+        // Follow the choices back down from the root.
+        let mut set = vec![];
+        let mut id = root;
+        while id > ONE {
+            let node = self.nodes[id as usize];
+            if best[&node.lo] == best[&id] {
+                id = node.lo;
+            } else {
+                set.push(node.var as usize);
+                id = node.hi;
+            }
+        }
+        set.reverse();
+        Some(set)
+    }
+
+    fn min_key_below(
+        &self,
+        id: NodeId,
+        keys: &[(u64, usize)],
+        best: &mut HashMap<NodeId, Option<(u64, usize, usize)>>,
+    ) -> Option<(u64, usize, usize)> {
+        if let Some(key) = best.get(&id) {
+            return *key;
+        }
+        let node = self.nodes[id as usize];
+        let lo = self.min_key_below(node.lo, keys, best);
+        let (cost, num_literals) = keys[node.var as usize];
+        let hi = self
+            .min_key_below(node.hi, keys, best)
+            .map(|(c, l, n)| (c + cost, l + num_literals, n + 1));
+        // Prefer the sets without `var` on a tie, as they're smaller numbers.
+        let key = match (lo, hi) {
+            (Some(lo), Some(hi)) => Some(lo.min(hi)),
+            (lo, hi) => lo.or(hi),
+        };
+        best.insert(id, key);
+        key
+    }
+}
@@ -0,0 +1,433 @@
+//! Read and write two-level logic in the Berkeley PLA format used by
+//! Espresso, for example:
+//!
+//! ```text
+//! # Two outputs of three inputs.
+//! .i 3
+//! .o 2
+//! .ilb a b c
+//! .ob f g
+//! .type fd
+//! 1-0 10
+//! -11 1-
+//! .e
+//! ```
+//!
+//! Each cube line has a character for each input, in the order of `.ilb`:
+//! `0` or `1` for a literal, or `-` if the input doesn't appear. These are
+//! followed by a character for each output, saying which set of that output
+//! the cube belongs to, as given by the `.type`:
+//!
+//! - `f`: `1` for the ON-set; `0` and `-` for neither. The rest of the
+//!   inputs are in the OFF-set.
+//! - `fd` (the default): `1` for the ON-set, `-` or `2` for the don't-care
+//!   set, `0` or `~` for neither. The rest are in the OFF-set.
+//! - `fr`: `1` for the ON-set, `0` for the OFF-set, `-` or `~` for neither.
+//!   The rest are don't-cares.
+//! - `fdr`: `1` for the ON-set, `0` for the OFF-set, `-` or `2` for the
+//!   don't-care set, `~` for neither. The rest are don't-cares.
+//!
+//! Inputs in both the ON-set and the don't-care set are don't-cares, while
+//! an input in the OFF-set and either of the others is an error.
+
+use std::{collections::HashMap, fmt::Write, ops::Range};
+
+use crate::{MAX_VARS, Minterm, error::ParseError};
+
+/// A multi-output function read from a PLA file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pla {
+    pub num_inputs: usize,
+    /// Names of the inputs from `.ilb`, or empty if not given.
+    pub input_labels: Vec<String>,
+    /// Names of the outputs from `.ob`, or empty if not given.
+    pub output_labels: Vec<String>,
+    pub outputs: Vec<PlaOutput>,
+}
+
+/// The minterms of one output of a PLA, each input combination being in
+/// exactly one of the sets. These can be passed to `get_prime_implicants`
+/// or `Minimizer::run` as the minterms and don't-cares.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaOutput {
+    pub on_set: Vec<Minterm>,
+    pub dont_cares: Vec<Minterm>,
+    pub off_set: Vec<Minterm>,
+}
+
+// The sets of the outputs that cubes can be given for, as from `.type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum PlaType {
+    F,
+    #[default]
+    Fd,
+    Fr,
+    Fdr,
+}
+
+impl PlaType {
+    const NAMES: [&str; 4] = ["f", "fd", "fr", "fdr"];
+
+    // Set that an output character of a cube puts the cube in, if any,
+    // or `None` if the character isn't valid for the type.
+    fn output_set(&self, ch: char) -> Option<Option<OutputSet>> {
+        use OutputSet::*;
+        match (self, ch) {
+            (_, '1') => Some(Some(On)),
+            (PlaType::F, '0' | '-') => Some(None),
+            (PlaType::Fd, '-' | '2') | (PlaType::Fdr, '-' | '2') => Some(Some(DontCare)),
+            (PlaType::Fd, '0' | '~') => Some(None),
+            (PlaType::Fr | PlaType::Fdr, '0') => Some(Some(Off)),
+            (PlaType::Fr, '-' | '~') | (PlaType::Fdr, '~') => Some(None),
+            _ => None,
+        }
+    }
+
+    fn output_chars(&self) -> &'static [&'static str] {
+        match self {
+            PlaType::F => &["1", "0", "-"],
+            PlaType::Fd => &["1", "-", "2", "0", "~"],
+            PlaType::Fr => &["1", "0", "-", "~"],
+            PlaType::Fdr => &["1", "0", "-", "2", "~"],
+        }
+    }
+}
+
+// Which set of an output an input combination is in. The order is that
+// of precedence, so don't-cares override the ON-set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum OutputSet {
+    On,
+    DontCare,
+    Off,
+}
+
+/// Parse the text of a PLA file, expanding the cubes of each output into its
+/// ON-set, don't-care set and OFF-set. Error spans are byte offsets in
+/// `pla_str`.
+pub fn parse_pla(pla_str: &str) -> Result<Pla, ParseError> {
+    let mut parser = PlaParser::default();
+    let mut offset = 0;
+    for line in pla_str.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        let content = line.split('#').next().unwrap();
+        let tokens = tokens(content, line_offset);
+        let Some((first, first_span)) = tokens.first().cloned() else {
+            continue;
+        };
+        if !first.starts_with('.') {
+            parser.add_cube(&tokens)?;
+            continue;
+        }
+        if first == ".e" || first == ".end" {
+            break;
+        }
+        parser.directive(first, first_span, &tokens[1..])?;
+    }
+    parser.finish(offset)
+}
+
+/// Write a PLA file of `.type f` for an output of each of `covers` of a
+/// function of `num_inputs` inputs, with the labels if they aren't empty.
+/// Cubes in the covers of several outputs share a line.
+pub fn string_for_pla(
+    covers: &[Vec<Minterm>],
+    num_inputs: usize,
+    input_labels: &[String],
+    output_labels: &[String],
+) -> String {
+    let mut lines: Vec<(Minterm, Vec<bool>)> = vec![];
+    let mut line_for_cube = HashMap::new();
+    for (output, cover) in covers.iter().enumerate() {
+        for cube in cover {
+            assert!(cube.num_vars() == num_inputs);
+            let line = *line_for_cube.entry(*cube).or_insert_with(|| {
+                lines.push((*cube, vec![false; covers.len()]));
+                lines.len() - 1
+            });
+            lines[line].1[output] = true;
+        }
+    }
+
+    let mut pla_string = String::new();
+    writeln!(pla_string, ".i {num_inputs}").unwrap();
+    writeln!(pla_string, ".o {}", covers.len()).unwrap();
+    if !input_labels.is_empty() {
+        writeln!(pla_string, ".ilb {}", input_labels.join(" ")).unwrap();
+    }
+    if !output_labels.is_empty() {
+        writeln!(pla_string, ".ob {}", output_labels.join(" ")).unwrap();
+    }
+    writeln!(pla_string, ".type f").unwrap();
+    writeln!(pla_string, ".p {}", lines.len()).unwrap();
+    for (cube, outputs) in lines {
+        let inputs = (0..num_inputs)
+            .map(|var| match cube.literal(var) {
+                Some(true) => '1',
+                Some(false) => '0',
+                None => '-',
+            })
+            .collect::<String>();
+        let outputs = outputs
+            .iter()
+            .map(|in_cover| if *in_cover { '1' } else { '0' })
+            .collect::<String>();
+        writeln!(pla_string, "{inputs} {outputs}").unwrap();
+    }
+    writeln!(pla_string, ".e").unwrap();
+    pla_string
+}
+
+// Split `line` into whitespace-separated tokens, with their byte spans in
+// the whole input given the offset of the line.
+fn tokens(line: &str, line_offset: usize) -> Vec<(&str, Range<usize>)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, ch) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, ch.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(token_start), true) => {
+                tokens.push((
+                    &line[token_start..i],
+                    line_offset + token_start..line_offset + i,
+                ));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+#[derive(Default)]
+struct PlaParser {
+    num_inputs: Option<usize>,
+    num_outputs: Option<usize>,
+    input_labels: Option<(Vec<String>, Range<usize>)>,
+    output_labels: Option<(Vec<String>, Range<usize>)>,
+    pla_type: PlaType,
+    /// The set of each input combination for each output, once the first
+    /// cube has been read.
+    output_sets: Vec<Vec<Option<OutputSet>>>,
+    num_cubes: usize,
+}
+
+impl PlaParser {
+    fn directive(
+        &mut self,
+        name: &str,
+        span: Range<usize>,
+        args: &[(&str, Range<usize>)],
+    ) -> Result<(), ParseError> {
+        let labels = || {
+            (
+                args.iter().map(|(arg, _)| arg.to_string()).collect(),
+                span.clone(),
+            )
+        };
+        match name {
+            ".i" | ".o" | ".type" if self.num_cubes > 0 => Err(ParseError::new(
+                span.clone(),
+                format!("'{name}' must come before the cubes"),
+            )),
+            ".i" => {
+                let num_inputs = parse_count(name, &span, args)?;
+                if num_inputs > MAX_VARS {
+                    return Err(ParseError::new(
+                        args[0].1.clone(),
+                        format!("number of inputs must be at most {MAX_VARS}"),
+                    ));
+                }
+                self.num_inputs = Some(num_inputs);
+                Ok(())
+            }
+            ".o" => {
+                self.num_outputs = Some(parse_count(name, &span, args)?);
+                Ok(())
+            }
+            ".ilb" => {
+                self.input_labels = Some(labels());
+                Ok(())
+            }
+            ".ob" => {
+                self.output_labels = Some(labels());
+                Ok(())
+            }
+            ".type" => {
+                let [(type_name, type_span)] = args else {
+                    return Err(ParseError::new(span, "expected one PLA type")
+                        .with_expected(&PlaType::NAMES));
+                };
+                self.pla_type = match *type_name {
+                    "f" => PlaType::F,
+                    "fd" => PlaType::Fd,
+                    "fr" => PlaType::Fr,
+                    "fdr" => PlaType::Fdr,
+                    _ => {
+                        return Err(ParseError::new(
+                            type_span.clone(),
+                            format!("unsupported PLA type '{type_name}'"),
+                        )
+                        .with_expected(&PlaType::NAMES));
+                    }
+                };
+                Ok(())
+            }
+            // The number of cubes is only a hint.
+            ".p" => parse_count(name, &span, args).map(|_| ()),
+            _ => Err(
+                ParseError::new(span, format!("unsupported PLA directive '{name}'"))
+                    .with_expected(&[".i", ".o", ".ilb", ".ob", ".type", ".p", ".e"]),
+            ),
+        }
+    }
+
+    fn add_cube(&mut self, tokens: &[(&str, Range<usize>)]) -> Result<(), ParseError> {
+        let (Some(num_inputs), Some(num_outputs)) = (self.num_inputs, self.num_outputs) else {
+            return Err(ParseError::new(
+                tokens[0].1.clone(),
+                "'.i' and '.o' must come before the cubes",
+            ));
+        };
+        if self.output_sets.is_empty() {
+            self.output_sets = vec![vec![None; 1 << num_inputs]; num_outputs];
+        }
+        self.num_cubes += 1;
+
+        // The inputs and outputs may be split by whitespace anywhere.
+        let chars = tokens
+            .iter()
+            .flat_map(|(token, span)| token.char_indices().map(|(i, ch)| (ch, span.start + i)))
+            .collect::<Vec<_>>();
+        let line_span = tokens[0].1.start..tokens.last().unwrap().1.end;
+        if chars.len() != num_inputs + num_outputs {
+            return Err(ParseError::new(
+                line_span,
+                format!(
+                    "expected {num_inputs} inputs and {num_outputs} outputs, found {} characters",
+                    chars.len()
+                ),
+            ));
+        }
+        let char_span = |pos: usize, ch: char| pos..pos + ch.len_utf8();
+
+        let mut cube = Minterm::new_true(num_inputs);
+        for (var, (ch, pos)) in chars[..num_inputs].iter().enumerate() {
+            let literal = match ch {
+                '0' => Some(false),
+                '1' => Some(true),
+                '-' => None,
+                _ => {
+                    return Err(ParseError::new(
+                        char_span(*pos, *ch),
+                        format!("invalid input value '{ch}'"),
+                    )
+                    .with_expected(&["0", "1", "-"]));
+                }
+            };
+            cube.set_literal(var, literal);
+        }
+
+        for (output, (ch, pos)) in chars[num_inputs..].iter().enumerate() {
+            let Some(set) = self.pla_type.output_set(*ch) else {
+                return Err(ParseError::new(
+                    char_span(*pos, *ch),
+                    format!("invalid output value '{ch}'"),
+                )
+                .with_expected(self.pla_type.output_chars()));
+            };
+            let Some(set) = set else {
+                continue;
+            };
+            for index in cube.covered_indices() {
+                let current = &mut self.output_sets[output][index];
+                if current
+                    .is_some_and(|current| (current == OutputSet::Off) != (set == OutputSet::Off))
+                {
+                    return Err(ParseError::new(
+                        char_span(*pos, *ch),
+                        format!("cube overlaps the OFF-set of output {}", output + 1),
+                    ));
+                }
+                *current = (*current).max(Some(set));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, end: usize) -> Result<Pla, ParseError> {
+        let (Some(num_inputs), Some(num_outputs)) = (self.num_inputs, self.num_outputs) else {
+            return Err(ParseError::new(end..end, "expected '.i' and '.o'"));
+        };
+        let input_labels = check_labels(self.input_labels, num_inputs, "inputs")?;
+        let output_labels = check_labels(self.output_labels, num_outputs, "outputs")?;
+
+        // Input combinations without a cube are in the OFF-set, unless
+        // it was given, in which case they are don't-cares.
+        let unset = match self.pla_type {
+            PlaType::F | PlaType::Fd => OutputSet::Off,
+            PlaType::Fr | PlaType::Fdr => OutputSet::DontCare,
+        };
+        let mut output_sets = self.output_sets;
+        output_sets.resize(num_outputs, vec![None; 1 << num_inputs]);
+        let outputs = output_sets
+            .into_iter()
+            .map(|sets| {
+                let mut output = PlaOutput::default();
+                for (index, set) in sets.into_iter().enumerate() {
+                    let minterm = Minterm::from_index(index, num_inputs);
+                    match set.unwrap_or(unset) {
+                        OutputSet::On => output.on_set.push(minterm),
+                        OutputSet::DontCare => output.dont_cares.push(minterm),
+                        OutputSet::Off => output.off_set.push(minterm),
+                    }
+                }
+                output
+            })
+            .collect();
+
+        Ok(Pla {
+            num_inputs,
+            input_labels,
+            output_labels,
+            outputs,
+        })
+    }
+}
+
+// Parse the single count argument of directive `name`.
+fn parse_count(
+    name: &str,
+    span: &Range<usize>,
+    args: &[(&str, Range<usize>)],
+) -> Result<usize, ParseError> {
+    let [(arg, arg_span)] = args else {
+        return Err(ParseError::new(
+            span.clone(),
+            format!("expected a number after '{name}'"),
+        ));
+    };
+    arg.parse()
+        .map_err(|_| ParseError::new(arg_span.clone(), format!("invalid number '{arg}'")))
+}
+
+// Check that labels from `.ilb` or `.ob`, if given, are one for each of the
+// `count` inputs or outputs.
+fn check_labels(
+    labels: Option<(Vec<String>, Range<usize>)>,
+    count: usize,
+    kind: &str,
+) -> Result<Vec<String>, ParseError> {
+    match labels {
+        None => Ok(vec![]),
+        Some((labels, _)) if labels.len() == count => Ok(labels),
+        Some((labels, span)) => Err(ParseError::new(
+            span,
+            format!(
+                "expected {count} labels for the {kind}, found {}",
+                labels.len()
+            ),
+        )),
+    }
+}
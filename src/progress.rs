@@ -0,0 +1,142 @@
+//! Cancellation and progress reporting for long minimizations. A `Monitor`
+//! is passed down through prime implicant generation and the solvers, which
+//! report each phase to its callback and stop with `QmError::Cancelled`
+//! once its `CancelToken` has been cancelled, or with
+//! `QmError::BudgetExceeded` once past its deadline.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
+
+use crate::error::QmError;
+
+/// A handle for cancelling a minimization, e.g. from another thread.
+/// Clones share the same cancellation state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the minimizations using this token to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// A phase of a minimization, with the sizes involved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Merging the column of the table holding the `num_terms` implicants
+    /// with `level` don't-cares, while generating prime implicants.
+    PrimeImplicants { level: usize, num_terms: usize },
+    /// Removed the essential prime implicants from the chart, leaving
+    /// `num_remaining_cols` columns to cover.
+    EssentialPrimeImplicants {
+        num_essential: usize,
+        num_remaining_cols: usize,
+    },
+    /// Multiplied in `column` of the `num_columns` sums of Petrick's method,
+    /// leaving `num_products` products.
+    PetrickColumn {
+        column: usize,
+        num_columns: usize,
+        num_products: usize,
+    },
+    /// Selected a row in the greedy cover, leaving `num_uncovered` columns.
+    GreedySelection {
+        num_selected: usize,
+        num_uncovered: usize,
+    },
+    /// Finished cover `restart` of the `num_restarts` restarts of the
+    /// improved greedy cover, with the cost of the best cover so far of
+    /// the columns left after the essential prime implicants.
+    GreedyRestart {
+        restart: usize,
+        num_restarts: usize,
+        best_cost: u64,
+    },
+    /// Searched `num_nodes` nodes of branch and bound, with the cost of the
+    /// best cover found so far.
+    BranchBoundNodes {
+        num_nodes: usize,
+        best_cost: Option<u64>,
+    },
+    /// Found a cover of the chart left after the essential prime implicants,
+    /// of cost `cost` with `num_literals` literals, after `num_conflicts`
+    /// conflicts of the SAT solver.
+    SatCover {
+        cost: u64,
+        num_literals: usize,
+        num_conflicts: u64,
+    },
+    /// Starting pass `pass` of the Espresso-style minimizer, with a cover
+    /// of `num_cubes` cubes of cost `cost`.
+    EspressoPass {
+        pass: usize,
+        num_cubes: usize,
+        cost: u64,
+    },
+}
+
+/// Cancellation token, progress callback and deadline for a minimization.
+/// Any of them may be left out; the default monitor does nothing.
+#[derive(Clone, Copy, Default)]
+pub struct Monitor<'a> {
+    pub cancel: Option<&'a CancelToken>,
+    pub progress: Option<&'a (dyn Fn(Progress) + Sync)>,
+    pub deadline: Option<Instant>,
+}
+
+impl<'a> Monitor<'a> {
+    /// A monitor that never cancels and ignores progress.
+    pub const NONE: Monitor<'static> = Monitor {
+        cancel: None,
+        progress: None,
+        deadline: None,
+    };
+
+    pub fn with_cancel(mut self, cancel: &'a CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: &'a (dyn Fn(Progress) + Sync)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Pass `progress` to the callback, if any.
+    pub fn report(&self, progress: Progress) {
+        if let Some(callback) = self.progress {
+            callback(progress);
+        }
+    }
+
+    /// Return `QmError::Cancelled` if the minimization has been cancelled,
+    /// or `QmError::BudgetExceeded` if it is past the deadline.
+    pub fn check_cancelled(&self) -> Result<(), QmError> {
+        match (self.cancel, self.deadline) {
+            (Some(cancel), _) if cancel.is_cancelled() => Err(QmError::Cancelled),
+            (_, Some(deadline)) if Instant::now() >= deadline => Err(QmError::BudgetExceeded),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! A small CDCL SAT solver, for solving the covering problem of the prime
+//! implicant chart exactly without an external solver. Clauses are watched
+//! by two literals for unit propagation, and each conflict is analyzed to
+//! learn a clause at its first unique implication point before backjumping.
+//! Decisions follow the most active variables, with their saved phases,
+//! and the search restarts on the Luby sequence.
+//!
+//! Clauses can be added between calls to `SatSolver::solve`, which also
+//! takes assumptions, so that a bound can be tightened incrementally while
+//! keeping the learnt clauses.
+
+use crate::{error::QmError, progress::Monitor};
+
+/// Number of conflicts in the unit of the Luby restart sequence.
+const RESTART_UNIT: u64 = 100;
+
+/// Number of conflicts between checks for cancellation.
+const CONFLICTS_PER_CHECK: u64 = 256;
+
+/// Factor by which the activity of later conflicts grows over earlier ones.
+const ACTIVITY_GROWTH: f64 = 1.0 / 0.95;
+
+/// A variable of the solver, as returned by `SatSolver::new_var`.
+pub type Var = u32;
+
+/// A variable or its negation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn pos(var: Var) -> Self {
+        Lit(var << 1)
+    }
+
+    pub fn neg(var: Var) -> Self {
+        Lit((var << 1) | 1)
+    }
+
+    pub fn var(self) -> Var {
+        self.0 >> 1
+    }
+
+    pub fn is_neg(self) -> bool {
+        self.0 & 1 != 0
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// Outcome of `SatSolver::solve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatResult {
+    /// The clauses and assumptions are satisfied by the model.
+    Sat,
+    /// No assignment satisfies the clauses and assumptions.
+    Unsat,
+    /// The conflict limit was reached first.
+    Unknown,
+}
+
+/// A CDCL SAT solver. See the module documentation.
+#[derive(Default)]
+pub struct SatSolver {
+    /// Clauses, each watched by its first two literals. A clause that is the
+    /// reason for an assignment has the assigned literal first.
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, to visit when it becomes false.
+    watches: Vec<Vec<usize>>,
+
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    /// Assigned literals in order, with the start of each decision level.
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    /// Position in the trail of the next literal to propagate.
+    qhead: usize,
+
+    activity: Vec<f64>,
+    activity_inc: f64,
+    /// Last value of each variable, used for its next decision.
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+
+    /// False once the clauses are unsatisfiable without any assumptions.
+    unsat: bool,
+    model: Vec<bool>,
+    /// Total number of conflicts over all calls to `solve`.
+    pub num_conflicts: u64,
+}
+
+impl SatSolver {
+    pub fn new() -> Self {
+        SatSolver {
+            activity_inc: 1.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn num_clauses(&self) -> usize {
+        self.clauses.len()
+    }
+
+    pub fn new_var(&mut self) -> Var {
+        let var = Var::try_from(self.values.len()).expect("too many SAT variables");
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.seen.push(false);
+        self.watches.extend([vec![], vec![]]);
+        var
+    }
+
+    /// Add the clause that one of `lits` is true. Returns false if the
+    /// clauses have become unsatisfiable.
+    pub fn add_clause(&mut self, lits: &[Lit]) -> bool {
+        if self.unsat {
+            return false;
+        }
+        self.backtrack(0);
+        let mut clause = lits.to_vec();
+        clause.sort_unstable();
+        clause.dedup();
+        // Skip tautologies and clauses already satisfied, and leave
+        // out literals that are already false.
+        if clause.windows(2).any(|pair| pair[0] == !pair[1])
+            || clause.iter().any(|lit| self.lit_value(*lit) == Some(true))
+        {
+            return true;
+        }
+        clause.retain(|lit| self.lit_value(*lit).is_none());
+
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.assign(clause[0], None);
+                self.unsat = self.propagate().is_some();
+            }
+            _ => {
+                self.attach(clause);
+            }
+        }
+        !self.unsat
+    }
+
+    /// Value of `var` in the model found by the last call to `solve`
+    /// that returned `SatResult::Sat`.
+    pub fn model_value(&self, var: Var) -> bool {
+        self.model[var as usize]
+    }
+
+    /// Search for an assignment satisfying the clauses with all of
+    /// `assumptions` true, giving up after `max_conflicts` conflicts if
+    /// given. The assumptions hold only for this call.
+    pub fn solve(
+        &mut self,
+        assumptions: &[Lit],
+        max_conflicts: Option<u64>,
+        monitor: &Monitor,
+    ) -> Result<SatResult, QmError> {
+        if self.unsat {
+            return Ok(SatResult::Unsat);
+        }
+        let result = self.search(assumptions, max_conflicts, monitor);
+        self.backtrack(0);
+        result
+    }
+
+    fn search(
+        &mut self,
+        assumptions: &[Lit],
+        max_conflicts: Option<u64>,
+        monitor: &Monitor,
+    ) -> Result<SatResult, QmError> {
+        let mut num_conflicts: u64 = 0;
+        let mut num_restarts = 0;
+        let mut restart_conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                num_conflicts += 1;
+                self.num_conflicts += 1;
+                restart_conflicts += 1;
+                if self.trail_lim.is_empty() {
+                    self.unsat = true;
+                    return Ok(SatResult::Unsat);
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                let asserting = learnt[0];
+                if learnt.len() == 1 {
+                    self.assign(asserting, None);
+                } else {
+                    let clause = self.attach(learnt);
+                    self.assign(asserting, Some(clause));
+                }
+                self.activity_inc *= ACTIVITY_GROWTH;
+
+                if num_conflicts.is_multiple_of(CONFLICTS_PER_CHECK) {
+                    monitor.check_cancelled()?;
+                }
+                if max_conflicts.is_some_and(|max| num_conflicts >= max) {
+                    return Ok(SatResult::Unknown);
+                }
+                continue;
+            }
+
+            if restart_conflicts >= RESTART_UNIT * luby(num_restarts) {
+                num_restarts += 1;
+                restart_conflicts = 0;
+                self.backtrack(0);
+            }
+
+            // Assume the assumptions first, each at its own level.
+            let mut decision = None;
+            while decision.is_none() && self.trail_lim.len() < assumptions.len() {
+                let lit = assumptions[self.trail_lim.len()];
+                match self.lit_value(lit) {
+                    Some(true) => self.trail_lim.push(self.trail.len()),
+                    Some(false) => return Ok(SatResult::Unsat),
+                    None => decision = Some(lit),
+                }
+            }
+            let decision = match decision {
+                Some(lit) => lit,
+                None => match self.pick_branch_var() {
+                    Some(var) if self.phases[var as usize] => Lit::pos(var),
+                    Some(var) => Lit::neg(var),
+                    None => {
+                        self.model = self.values.iter().map(|v| v.unwrap()).collect();
+                        return Ok(SatResult::Sat);
+                    }
+                },
+            };
+            self.trail_lim.push(self.trail.len());
+            self.assign(decision, None);
+        }
+    }
+
+    fn lit_value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var() as usize].map(|value| value != lit.is_neg())
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var() as usize;
+        self.values[var] = Some(!lit.is_neg());
+        self.levels[var] = self.trail_lim.len();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    // Add a clause of at least two literals, watching the first two.
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    // Undo the assignments above decision level `level`.
+    fn backtrack(&mut self, level: usize) {
+        if self.trail_lim.len() <= level {
+            return;
+        }
+        for lit in self.trail.drain(self.trail_lim[level]..) {
+            let var = lit.var() as usize;
+            self.values[var] = None;
+            self.reasons[var] = None;
+            self.phases[var] = !lit.is_neg();
+        }
+        self.trail_lim.truncate(level);
+        self.qhead = self.trail.len();
+    }
+
+    // Assign the literals implied by unit clauses, returning the
+    // clause that is false if there is a conflict.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = !self.trail[self.qhead];
+            self.qhead += 1;
+            let mut watchers = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watchers.len() {
+                let index = watchers[i];
+                let clause = &mut self.clauses[index];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                let first_value = self.values[first.var() as usize].map(|v| v != first.is_neg());
+                if first_value == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                // Watch another literal that isn't false, if there is one.
+                let values = &self.values;
+                let other = clause[2..].iter().position(|lit| {
+                    values[lit.var() as usize].is_none_or(|value| value != lit.is_neg())
+                });
+                if let Some(k) = other {
+                    clause.swap(1, k + 2);
+                    self.watches[clause[1].index()].push(index);
+                    watchers.swap_remove(i);
+                } else if first_value == Some(false) {
+                    conflict = Some(index);
+                    break;
+                } else {
+                    self.assign(first, Some(index));
+                    i += 1;
+                }
+            }
+            self.watches[false_lit.index()] = watchers;
+            if conflict.is_some() {
+                self.qhead = self.trail.len();
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // Learn a clause from the conflict at its first unique implication
+    // point, returning it with the asserting literal first, along with
+    // the level to backjump to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let level = self.trail_lim.len();
+        let mut learnt = vec![Lit(0)];
+        let mut num_at_level = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut implied = None;
+        loop {
+            // Skip the implied literal, which is first in its reason.
+            let skip = usize::from(implied.is_some());
+            for i in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][i];
+                let var = lit.var() as usize;
+                if self.seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.bump_activity(var);
+                if self.levels[var] == level {
+                    num_at_level += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+
+            // Continue from the most recent assignment in the conflict.
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var() as usize] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var() as usize] = false;
+            num_at_level -= 1;
+            if num_at_level == 0 {
+                learnt[0] = !lit;
+                break;
+            }
+            implied = Some(lit);
+            clause = self.reasons[lit.var() as usize].unwrap();
+        }
+
+        for lit in &learnt[1..] {
+            self.seen[lit.var() as usize] = false;
+        }
+        // Watch a literal of the highest remaining level second, so that
+        // the clause is unit after backjumping to that level.
+        let mut backjump_level = 0;
+        if let Some(i) = (1..learnt.len()).max_by_key(|i| self.levels[learnt[*i].var() as usize]) {
+            learnt.swap(1, i);
+            backjump_level = self.levels[learnt[1].var() as usize];
+        }
+        (learnt, backjump_level)
+    }
+
+    fn bump_activity(&mut self, var: usize) {
+        self.activity[var] += self.activity_inc;
+        if self.activity[var] > 1e100 {
+            for activity in &mut self.activity {
+                *activity *= 1e-100;
+            }
+            self.activity_inc *= 1e-100;
+        }
+    }
+
+    // The unassigned variable of highest activity.
+    fn pick_branch_var(&self) -> Option<Var> {
+        (0..self.values.len())
+            .filter(|var| self.values[*var].is_none())
+            .max_by(|a, b| self.activity[*a].total_cmp(&self.activity[*b]))
+            .map(|var| var as Var)
+    }
+}
+
+// Term `i` of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ...
+fn luby(mut i: u64) -> u64 {
+    // Find the finished subsequence that `i` is in, of length 2^k - 1.
+    let mut len = 1;
+    let mut k = 0;
+    while len < i + 1 {
+        len = 2 * len + 1;
+        k += 1;
+    }
+    while len - 1 != i {
+        len = (len - 1) / 2;
+        k -= 1;
+        i %= len;
+    }
+    1 << k
+}
@@ -0,0 +1,353 @@
+//! Find an exact minimum cover of the prime implicant chart with the
+//! in-crate SAT solver. Each remaining prime implicant is a variable, and
+//! each column a clause that one of its rows is chosen. A totalizer counts
+//! the cost of the chosen rows in unary, and the bound on the cost is
+//! tightened after each cover found, until the solver proves that no
+//! cheaper cover exists. The number of literals is then minimized in the
+//! same way among the covers of least cost.
+//!
+//! Unlike Petrick's method this doesn't expand the product of sums, and
+//! the learnt clauses are kept from one bound to the next.
+
+use crate::{
+    Minterm, PrimeImplicateChart,
+    cost::{CostModel, ProductCount},
+    error::QmError,
+    petrick::remove_essential_prime_impls,
+    progress::{Monitor, Progress},
+    sat::{Lit, SatResult, SatSolver, Var},
+};
+
+/// Largest totalizer we build, counting both its unary literals and its
+/// clauses. Costs that are large and without a common factor would need
+/// a bigger one, and fail with `QmError::BudgetExceeded` instead.
+const MAX_TOTALIZER_SIZE: usize = 1 << 21;
+
+/// Get a minimal set of prime implicants for an equivalent expression.
+pub fn get_minimal_sop_terms(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+) -> Result<(Vec<Minterm>, SatStats), QmError> {
+    get_minimal_sop_terms_with_cost(prime_impl_chart, prime_impls, &ProductCount)
+}
+
+/// Get a set of prime implicants for an equivalent expression
+/// that is of minimal cost according to `cost`.
+pub fn get_minimal_sop_terms_with_cost(
+    prime_impl_chart: PrimeImplicateChart,
+    prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+) -> Result<(Vec<Minterm>, SatStats), QmError> {
+    get_minimal_sop_terms_with_limit(prime_impl_chart, prime_impls, cost, None, &Monitor::NONE)
+}
+
+/// Like `get_minimal_sop_terms_with_cost`, but giving up after the SAT
+/// solver reaches `max_conflicts` conflicts, if given, in which case the
+/// best cover found is returned and `SatStats::is_optimal` is not set.
+/// Each better cover found is reported to `monitor`, stopping with
+/// `QmError::Cancelled` if it is cancelled. Fails with
+/// `QmError::BudgetExceeded` if the costs are too large to count.
+pub fn get_minimal_sop_terms_with_limit(
+    mut prime_impl_chart: PrimeImplicateChart,
+    mut prime_impls: Vec<Minterm>,
+    cost: &dyn CostModel,
+    max_conflicts: Option<u64>,
+    monitor: &Monitor,
+) -> Result<(Vec<Minterm>, SatStats), QmError> {
+    prime_impl_chart.check_shape(&prime_impls)?;
+
+    // Remove essential prime implicants from chart.
+    let (mut min_expr_terms, remaining_cols) =
+        remove_essential_prime_impls(&mut prime_impl_chart, &mut prime_impls, None)?;
+    monitor.report(Progress::EssentialPrimeImplicants {
+        num_essential: min_expr_terms.len(),
+        num_remaining_cols: remaining_cols.len(),
+    });
+
+    let col_rows = remaining_cols
+        .iter()
+        .map(|col| {
+            let rows = prime_impl_chart.rows.iter().enumerate();
+            rows.filter(|(_, row)| row[*col])
+                .map(|(row_i, _)| row_i)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let row_costs = prime_impls
+        .iter()
+        .map(|term| cost.term_cost(term))
+        .collect::<Vec<_>>();
+    let row_literals = prime_impls
+        .iter()
+        .map(|term| term.num_literals() as u64)
+        .collect::<Vec<_>>();
+
+    let mut search = CoverSearch::new(prime_impls.len(), &col_rows, max_conflicts, monitor);
+    // Choosing every row is a cover, which we make irredundant to
+    // start from a better bound.
+    let all_rows = vec![true; prime_impls.len()];
+    let mut best = search.irredundant(all_rows, &row_costs, &row_literals);
+    search.report(&best, &row_costs, &row_literals);
+
+    // Minimize the cost, and then the number of literals for that cost.
+    let cost_is_optimal = search.minimize(&mut best, &row_costs, &row_costs, &row_literals)?;
+    let is_optimal =
+        cost_is_optimal && search.minimize(&mut best, &row_literals, &row_costs, &row_literals)?;
+
+    min_expr_terms.extend(
+        prime_impls
+            .into_iter()
+            .zip(best)
+            .filter(|(_, chosen)| *chosen)
+            .map(|(term, _)| term),
+    );
+    let stats = SatStats {
+        is_optimal,
+        num_vars: search.solver.num_vars(),
+        num_clauses: search.solver.num_clauses(),
+        num_solves: search.num_solves,
+        num_conflicts: search.solver.num_conflicts,
+    };
+    Ok((min_expr_terms, stats))
+}
+
+/// Statistics about a run of the SAT cover search.
+#[derive(Debug, Default)]
+pub struct SatStats {
+    /// Whether the SAT solver proved that the cover is of least cost,
+    /// and has the fewest literals among those covers.
+    pub is_optimal: bool,
+    pub num_vars: usize,
+    pub num_clauses: usize,
+    /// Number of calls to the SAT solver, one for each bound.
+    pub num_solves: usize,
+    pub num_conflicts: u64,
+}
+
+/// The covering problem of a chart, encoded in a SAT solver.
+struct CoverSearch<'a> {
+    solver: SatSolver,
+    /// Variable for choosing each row.
+    row_vars: Vec<Var>,
+    col_rows: &'a [Vec<usize>],
+    max_conflicts: Option<u64>,
+    monitor: &'a Monitor<'a>,
+    num_solves: usize,
+}
+
+impl<'a> CoverSearch<'a> {
+    fn new(
+        num_rows: usize,
+        col_rows: &'a [Vec<usize>],
+        max_conflicts: Option<u64>,
+        monitor: &'a Monitor<'a>,
+    ) -> Self {
+        let mut solver = SatSolver::new();
+        let row_vars = (0..num_rows).map(|_| solver.new_var()).collect::<Vec<_>>();
+        for rows in col_rows {
+            let lits = rows
+                .iter()
+                .map(|row| Lit::pos(row_vars[*row]))
+                .collect::<Vec<_>>();
+            solver.add_clause(&lits);
+        }
+        CoverSearch {
+            solver,
+            row_vars,
+            col_rows,
+            max_conflicts,
+            monitor,
+            num_solves: 0,
+        }
+    }
+
+    // Tighten the bound on the total of `weights` over the chosen rows until
+    // no better cover than `best` exists, updating `best` with each better
+    // cover found. Returns whether `best` was proven to be minimal, or false
+    // if the conflict limit was reached. Afterward the bound is kept, so
+    // only covers as good as `best` remain.
+    fn minimize(
+        &mut self,
+        best: &mut Vec<bool>,
+        weights: &[u64],
+        row_costs: &[u64],
+        row_literals: &[u64],
+    ) -> Result<bool, QmError> {
+        // Only the ratios of the weights matter, and smaller weights give
+        // a smaller totalizer.
+        let divisor = weights
+            .iter()
+            .fold(0, |divisor, weight| gcd(divisor, *weight));
+        let weights = weights
+            .iter()
+            .map(|weight| weight / divisor.max(1))
+            .collect::<Vec<_>>();
+        let mut best_total = total(best, &weights);
+        // Outputs up to one past the bound, so that `best_total` can be kept.
+        let max_outputs = best_total as usize + 1;
+        if totalizer_size(&weights, max_outputs).1 > MAX_TOTALIZER_SIZE {
+            return Err(QmError::BudgetExceeded);
+        }
+        let inputs = self
+            .row_vars
+            .iter()
+            .zip(&weights)
+            .map(|(var, weight)| (Lit::pos(*var), *weight))
+            .collect::<Vec<_>>();
+        let at_least = totalizer(&mut self.solver, &inputs, max_outputs);
+
+        let is_minimal = loop {
+            // Keep covers no worse than the best, and look for a better one.
+            // If the total can't exceed the best, there is nothing to keep.
+            if let Some(lit) = at_least.get(best_total as usize) {
+                self.solver.add_clause(&[!*lit]);
+            }
+            if best_total == 0 {
+                break true;
+            }
+            // The conflict limit is for the whole search, not each bound.
+            let conflicts_left = self
+                .max_conflicts
+                .map(|max| max.saturating_sub(self.solver.num_conflicts));
+            if conflicts_left == Some(0) {
+                break false;
+            }
+            self.monitor.check_cancelled()?;
+            self.num_solves += 1;
+            let bound = !at_least[best_total as usize - 1];
+            match self.solver.solve(&[bound], conflicts_left, self.monitor)? {
+                SatResult::Sat => {
+                    let chosen = self
+                        .row_vars
+                        .iter()
+                        .map(|var| self.solver.model_value(*var))
+                        .collect();
+                    *best = self.irredundant(chosen, row_costs, row_literals);
+                    best_total = total(best, &weights);
+                    self.report(best, row_costs, row_literals);
+                }
+                SatResult::Unsat => break true,
+                SatResult::Unknown => break false,
+            }
+        };
+        Ok(is_minimal)
+    }
+
+    // Leave out chosen rows whose columns are covered by other chosen rows,
+    // trying the most costly rows first.
+    fn irredundant(
+        &self,
+        mut chosen: Vec<bool>,
+        row_costs: &[u64],
+        row_literals: &[u64],
+    ) -> Vec<bool> {
+        let mut num_covering = vec![0; self.col_rows.len()];
+        let mut row_cols = vec![vec![]; chosen.len()];
+        for (col, rows) in self.col_rows.iter().enumerate() {
+            for row in rows {
+                row_cols[*row].push(col);
+                if chosen[*row] {
+                    num_covering[col] += 1;
+                }
+            }
+        }
+        let mut rows = (0..chosen.len())
+            .filter(|row| chosen[*row])
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| std::cmp::Reverse((row_costs[*row], row_literals[*row])));
+        for row in rows {
+            if row_cols[row].iter().all(|col| num_covering[*col] > 1) {
+                chosen[row] = false;
+                for col in &row_cols[row] {
+                    num_covering[*col] -= 1;
+                }
+            }
+        }
+        chosen
+    }
+
+    fn report(&self, chosen: &[bool], row_costs: &[u64], row_literals: &[u64]) {
+        self.monitor.report(Progress::SatCover {
+            cost: total(chosen, row_costs),
+            num_literals: total(chosen, row_literals) as usize,
+            num_conflicts: self.solver.num_conflicts,
+        });
+    }
+}
+
+// Total of `weights` over the chosen rows.
+fn total(chosen: &[bool], weights: &[u64]) -> u64 {
+    chosen
+        .iter()
+        .zip(weights)
+        .filter(|(chosen, _)| **chosen)
+        .map(|(_, weight)| weight)
+        .sum()
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// Encode the total of the weights of the true `inputs` in unary, returning
+// literals where literal `i` is true if the total is more than `i`. Only
+// the first `max_outputs` are given, as the last one is true whenever the
+// total is at least `max_outputs`.
+//
+// A literal with weight `w` counts as `w` copies of it, and the counts of
+// the two halves of the inputs are merged by clauses that imply each count
+// is at least the sum of the counts of the halves.
+fn totalizer(solver: &mut SatSolver, inputs: &[(Lit, u64)], max_outputs: usize) -> Vec<Lit> {
+    match inputs {
+        [] => vec![],
+        [(lit, weight)] => vec![*lit; (*weight as usize).min(max_outputs)],
+        _ => {
+            let (left, right) = inputs.split_at(inputs.len() / 2);
+            let left = totalizer(solver, left, max_outputs);
+            let right = totalizer(solver, right, max_outputs);
+            let num_outputs = (left.len() + right.len()).min(max_outputs);
+            let outputs = (0..num_outputs)
+                .map(|_| Lit::pos(solver.new_var()))
+                .collect::<Vec<_>>();
+            for (i, lit) in left.iter().enumerate() {
+                solver.add_clause(&[!*lit, outputs[i]]);
+            }
+            for (j, lit) in right.iter().enumerate() {
+                solver.add_clause(&[!*lit, outputs[j]]);
+            }
+            for (i, left_lit) in left.iter().enumerate() {
+                for (j, right_lit) in right.iter().enumerate() {
+                    let sum = (i + j + 1).min(num_outputs - 1);
+                    solver.add_clause(&[!*left_lit, !*right_lit, outputs[sum]]);
+                }
+            }
+            outputs
+        }
+    }
+}
+
+// Number of outputs of `totalizer` for inputs of `weights`, and its size:
+// the number of literals in its unary counts plus the number of clauses,
+// so that the size can be checked before building it.
+fn totalizer_size(weights: &[u64], max_outputs: usize) -> (usize, usize) {
+    match weights {
+        [] => (0, 0),
+        [weight] => {
+            let num_outputs = (*weight).min(max_outputs as u64) as usize;
+            (num_outputs, num_outputs)
+        }
+        _ => {
+            let (left, right) = weights.split_at(weights.len() / 2);
+            let (left_outputs, left_size) = totalizer_size(left, max_outputs);
+            let (right_outputs, right_size) = totalizer_size(right, max_outputs);
+            let num_outputs = (left_outputs + right_outputs).min(max_outputs);
+            let num_clauses = left_outputs
+                .saturating_mul(right_outputs)
+                .saturating_add(left_outputs + right_outputs);
+            let size = [left_size, right_size, num_outputs, num_clauses]
+                .into_iter()
+                .fold(0, usize::saturating_add);
+            (num_outputs, size)
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! A common interface for the algorithms that choose a cover of the prime
+//! implicant chart, so that the rest of the pipeline doesn't depend on which
+//! one is used. Callers can implement `CoverSolver` for their own algorithms,
+//! and add them to a `SolverRegistry` to select them by name.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    Minterm, PrimeImplicateChart, branch_bound,
+    cost::CostModel,
+    error::QmError,
+    greedy_min_sop::{self, GreedyOptions},
+    petrick::{self, PetrickBudget, PetrickTimeInfo},
+    petrick_zdd,
+    progress::Monitor,
+    sat_cover,
+};
+
+// ---------------------
+// Solver trait and types.
+
+/// An algorithm for choosing a set of prime implicants covering
+/// all the columns of a prime implicant chart.
+pub trait CoverSolver {
+    /// Name for selecting the solver, e.g. in the `qm` program.
+    fn name(&self) -> &str;
+
+    /// Choose a cover of the chart, whose rows are `prime_impls`, trying
+    /// to minimize the total cost of its terms according to `cost`. Progress
+    /// is reported to `monitor`, stopping with `QmError::Cancelled` if it
+    /// is cancelled.
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError>;
+}
+
+/// A cover chosen by a solver.
+#[derive(Debug)]
+pub struct CoverSolution {
+    pub terms: Vec<Minterm>,
+    pub stats: SolverStats,
+}
+
+/// Statistics about a run of a solver.
+#[derive(Debug, Default)]
+pub struct SolverStats {
+    /// Total time taken by the solver.
+    pub time: Duration,
+    /// Whether the cover is known to be of minimal cost.
+    pub is_minimal: bool,
+    /// Whether the solver exceeded its budget, and so gave a cover that
+    /// isn't proven minimal, e.g. from a faster fallback algorithm.
+    pub budget_exceeded: bool,
+    /// Time spent in each step of Petrick's method, if used.
+    pub petrick_time: Option<PetrickTimeInfo>,
+}
+
+// -----------------------
+// Built-in solvers.
+
+/// Petrick's method, giving a cover of minimal cost. If the method exceeds
+/// its `budget`, the greedy cover is given instead, which is not known
+/// to be minimal.
+#[derive(Default)]
+pub struct Petrick {
+    pub budget: PetrickBudget,
+}
+
+impl Petrick {
+    /// Petrick's method with no budget, so always giving a minimal cover.
+    pub const EXACT: Petrick = Petrick {
+        budget: PetrickBudget::UNLIMITED,
+    };
+
+    pub fn with_budget(budget: PetrickBudget) -> Self {
+        Petrick { budget }
+    }
+}
+
+impl CoverSolver for Petrick {
+    fn name(&self) -> &str {
+        "petrick"
+    }
+
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError> {
+        let start = Instant::now();
+        // Keep a copy of the chart for the fallback, if it may be needed.
+        let fallback = (self.budget.timeout.is_some() || self.budget.max_products.is_some())
+            .then(|| (prime_impl_chart.clone(), prime_impls.clone()));
+        match petrick::get_minimal_sop_terms_with_budget(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            &self.budget,
+            monitor,
+        ) {
+            Ok((terms, petrick_time)) => Ok(CoverSolution {
+                terms,
+                stats: SolverStats {
+                    time: start.elapsed(),
+                    is_minimal: true,
+                    petrick_time: Some(petrick_time),
+                    ..Default::default()
+                },
+            }),
+            // Over a budget of our own, fall back to the greedy cover, which
+            // stops too if it was the monitor's deadline that passed.
+            Err(QmError::BudgetExceeded) if fallback.is_some() => {
+                let (prime_impl_chart, prime_impls) = fallback.unwrap();
+                let terms = greedy_min_sop::get_minimal_sops_with_monitor(
+                    prime_impl_chart,
+                    prime_impls,
+                    cost,
+                    monitor,
+                )?;
+                Ok(CoverSolution {
+                    terms,
+                    stats: SolverStats {
+                        time: start.elapsed(),
+                        budget_exceeded: true,
+                        ..Default::default()
+                    },
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Petrick's method on a zero-suppressed decision diagram, giving the same
+/// cover as `Petrick` while using less memory for the expanded products.
+pub struct PetrickZdd;
+
+impl CoverSolver for PetrickZdd {
+    fn name(&self) -> &str {
+        "petrick-zdd"
+    }
+
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError> {
+        let start = Instant::now();
+        let (terms, _) = petrick_zdd::get_minimal_sop_terms_with_monitor(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            monitor,
+        )?;
+        Ok(CoverSolution {
+            terms,
+            stats: SolverStats {
+                time: start.elapsed(),
+                is_minimal: true,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// The greedy set cover algorithm, giving an approximately minimal cover.
+pub struct Greedy;
+
+impl CoverSolver for Greedy {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError> {
+        let start = Instant::now();
+        let terms = greedy_min_sop::get_minimal_sops_with_monitor(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            monitor,
+        )?;
+        Ok(CoverSolution {
+            terms,
+            stats: SolverStats {
+                time: start.elapsed(),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// The improved greedy cover, with randomized restarts and removal of
+/// redundant rows, giving an approximately minimal cover that is usually
+/// closer to minimal than `Greedy`.
+#[derive(Default)]
+pub struct ImprovedGreedy {
+    pub options: GreedyOptions,
+}
+
+impl ImprovedGreedy {
+    pub fn with_options(options: GreedyOptions) -> Self {
+        ImprovedGreedy { options }
+    }
+}
+
+impl CoverSolver for ImprovedGreedy {
+    fn name(&self) -> &str {
+        "improved-greedy"
+    }
+
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError> {
+        let start = Instant::now();
+        let terms = greedy_min_sop::get_improved_sops(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            &self.options,
+            monitor,
+        )?;
+        Ok(CoverSolution {
+            terms,
+            stats: SolverStats {
+                time: start.elapsed(),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Branch and bound with chart reduction, giving a cover of minimal cost.
+pub struct BranchBound;
+
+impl CoverSolver for BranchBound {
+    fn name(&self) -> &str {
+        "branch-bound"
+    }
+
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError> {
+        let start = Instant::now();
+        let terms = branch_bound::get_minimal_sop_terms_with_monitor(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            monitor,
+        )?;
+        Ok(CoverSolution {
+            terms,
+            stats: SolverStats {
+                time: start.elapsed(),
+                is_minimal: true,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Exact cover by the in-crate SAT solver, tightening a bound on the cost
+/// until no cheaper cover exists. If the SAT solver reaches `max_conflicts`
+/// conflicts first, the best cover found is given, which is not proven
+/// to be minimal.
+#[derive(Default)]
+pub struct SatCover {
+    pub max_conflicts: Option<u64>,
+}
+
+impl SatCover {
+    /// The SAT search with no conflict limit, so always giving a minimal cover.
+    pub const EXACT: SatCover = SatCover {
+        max_conflicts: None,
+    };
+
+    pub fn with_max_conflicts(max_conflicts: u64) -> Self {
+        SatCover {
+            max_conflicts: Some(max_conflicts),
+        }
+    }
+}
+
+impl CoverSolver for SatCover {
+    fn name(&self) -> &str {
+        "sat"
+    }
+
+    fn solve(
+        &self,
+        prime_impl_chart: PrimeImplicateChart,
+        prime_impls: Vec<Minterm>,
+        cost: &dyn CostModel,
+        monitor: &Monitor,
+    ) -> Result<CoverSolution, QmError> {
+        let start = Instant::now();
+        let (terms, sat_stats) = sat_cover::get_minimal_sop_terms_with_limit(
+            prime_impl_chart,
+            prime_impls,
+            cost,
+            self.max_conflicts,
+            monitor,
+        )?;
+        Ok(CoverSolution {
+            terms,
+            stats: SolverStats {
+                time: start.elapsed(),
+                is_minimal: sat_stats.is_optimal,
+                budget_exceeded: !sat_stats.is_optimal,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+// ------------------
+// Solver registry.
+
+/// A set of solvers that can be selected by name.
+pub struct SolverRegistry {
+    solvers: Vec<Box<dyn CoverSolver>>,
+}
+
+impl Default for SolverRegistry {
+    /// A registry with the built-in solvers.
+    fn default() -> Self {
+        SolverRegistry {
+            solvers: vec![
+                Box::new(Petrick::EXACT),
+                Box::new(PetrickZdd),
+                Box::new(Greedy),
+                Box::new(ImprovedGreedy::default()),
+                Box::new(BranchBound),
+                Box::new(SatCover::EXACT),
+            ],
+        }
+    }
+}
+
+impl SolverRegistry {
+    /// Add a solver, replacing any existing solver of the same name.
+    pub fn register(&mut self, solver: Box<dyn CoverSolver>) {
+        self.solvers.retain(|s| s.name() != solver.name());
+        self.solvers.push(solver);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CoverSolver> {
+        self.solvers
+            .iter()
+            .find(|solver| solver.name() == name)
+            .map(|solver| &**solver)
+    }
+
+    /// Names of the registered solvers, in the order they were added.
+    pub fn names(&self) -> Vec<&str> {
+        self.solvers.iter().map(|solver| solver.name()).collect()
+    }
+}
@@ -66,3 +66,20 @@ fn test_case_d() -> Vec<Minterm> {
     ];
     minterms
 }
+
+/// Same as `test_case_c`, but with m9 and m14 as don't-cares.
+fn test_case_c_dont_cares() -> (Vec<Minterm>, Vec<Minterm>) {
+    let minterms: Vec<Minterm> = vec![
+        "0100".into(), // m4
+        "1000".into(), // m8
+        "1010".into(), // m10
+        "1011".into(), // m11
+        "1100".into(), // m12
+        "1111".into(), // m15
+    ];
+    let dont_cares: Vec<Minterm> = vec![
+        "1001".into(), // m9
+        "1110".into(), // m14
+    ];
+    (minterms, dont_cares)
+}